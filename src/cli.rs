@@ -0,0 +1,392 @@
+use clap::{Parser, ValueEnum};
+use rusttracing::color::antialias::ReconstructionFilter;
+use rusttracing::color::filter::Filter;
+use rusttracing::color::gamma::{GammaCorrection, SrgbEncode};
+use rusttracing::color::tonemap::{AcesFitted, Hable, Reinhard};
+use rusttracing::image::{ExrPrecision, ImageFormat};
+use rusttracing::scene::description::SceneDescription;
+use rusttracing::scene::sampler::SamplerKind;
+use rusttracing::settings::RenderSettings;
+use rusttracing::world::LightSampling;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Renders a scene description file to an image, or progressively to a preview window
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Scene description file, the built in default scene is rendered when left out
+    pub scene: Option<PathBuf>,
+
+    /// File the render is written to
+    #[arg(short, long, default_value = "render.ppm", conflicts_with = "preview")]
+    pub output: PathBuf,
+
+    /// Image format of the output file, guessed from its extension when left out
+    #[arg(short, long, value_enum, conflicts_with = "preview")]
+    pub format: Option<OutputFormat>,
+
+    /// Width of the render in pixels, overrides the scene. The scene's aspect ratio is kept if no height is given
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+
+    /// Height of the render in pixels, overrides the scene. The scene's aspect ratio is kept if no width is given
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: Option<u32>,
+
+    /// Samples per pixel, overrides the scene
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub spp: Option<u64>,
+
+    /// Sampler placing the samples in the pixel, on the lens and at every bounce: independent, stratified, halton or
+    /// sobol, the default
+    #[arg(long)]
+    pub sampler: Option<SamplerKind>,
+
+    /// Pixel reconstruction filter weighing the samples into pixels: box, tent, gaussian, mitchell or lanczos, with an
+    /// optional radius in pixels, e.g. `gaussian=1.5`. Defaults to box=0.5, the plain average of the samples of each
+    /// pixel
+    #[arg(long, value_name = "FILTER[=RADIUS]")]
+    pub pixel_filter: Option<ReconstructionFilter>,
+
+    /// How the light straight from the lights is found: bsdf, by the bounces alone, lights, by sampling the lights
+    /// alone, or mis, the default, combining both with multiple importance sampling
+    #[arg(long)]
+    pub light_sampling: Option<LightSampling>,
+
+    /// Maximum number of bounces per ray, overrides the scene
+    #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub depth: Option<u64>,
+
+    /// Seed for the random number generators, overrides the scene
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Number of render threads, all available cores by default
+    #[arg(short = 'j', long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub threads: Option<u64>,
+
+    /// Render progressively in a preview window instead of writing a file
+    #[arg(short, long)]
+    pub preview: bool,
+
+    /// Post-processing filter applied to the render, in order. Can be given several times, `none` disables them.
+    /// Filters: srgb, gamma[=EXPONENT], and the tone mapping operators reinhard[=EXPOSURE],
+    /// reinhard-extended=WHITE[,EXPOSURE], hable[=EXPOSURE] and aces[=EXPOSURE], exposures being in stops. The render
+    /// is linear, tone mapping goes before encoding it, e.g. `--filter aces --filter srgb`. Defaults to srgb for 8-bit
+    /// formats and none for high dynamic range ones, which are written linear
+    #[arg(long = "filter", value_name = "FILTER")]
+    pub filters: Vec<FilterSpec>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Binary portable pixmap
+    Ppm,
+    /// 8-bit PNG
+    Png,
+    /// Portable float map
+    Pfm,
+    /// Radiance RGBE
+    Hdr,
+    /// OpenEXR with 32-bit floats
+    Exr,
+    /// OpenEXR with 16-bit floats
+    ExrHalf,
+}
+
+impl OutputFormat {
+    fn from_extension(path: &Path) -> Option<Self> {
+        match ImageFormat::from_extension(path)? {
+            ImageFormat::Ppm => Some(OutputFormat::Ppm),
+            ImageFormat::Png => Some(OutputFormat::Png),
+            ImageFormat::Pfm => Some(OutputFormat::Pfm),
+            ImageFormat::Hdr => Some(OutputFormat::Hdr),
+            ImageFormat::Exr(_) => Some(OutputFormat::Exr),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Ppm => "ppm",
+            OutputFormat::Png => "png",
+            OutputFormat::Pfm => "pfm",
+            OutputFormat::Hdr => "hdr",
+            OutputFormat::Exr | OutputFormat::ExrHalf => "exr",
+        }
+    }
+}
+
+impl From<OutputFormat> for ImageFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Ppm => ImageFormat::Ppm,
+            OutputFormat::Png => ImageFormat::Png,
+            OutputFormat::Pfm => ImageFormat::Pfm,
+            OutputFormat::Hdr => ImageFormat::Hdr,
+            OutputFormat::Exr => ImageFormat::Exr(ExrPrecision::Float),
+            OutputFormat::ExrHalf => ImageFormat::Exr(ExrPrecision::Half),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterSpec {
+    None,
+    Gamma(f64),
+    /// sRGB encoding, what 8-bit images are expected to hold
+    Srgb,
+    /// Tone mapping operators, with their exposure in stops
+    Reinhard(f64),
+    ReinhardExtended { white: f64, exposure: f64 },
+    Hable(f64),
+    Aces(f64),
+}
+
+// Every filter the command line knows about, as shown in the errors
+const FILTERS: &str = "srgb, gamma[=EXPONENT], reinhard[=EXPOSURE], reinhard-extended=WHITE[,EXPOSURE], hable[=EXPOSURE], \
+                       aces[=EXPOSURE] or none";
+
+impl FromStr for FilterSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, params) = match s.split_once('=') {
+            Some((name, params)) => (name, params.split(',').collect()),
+            None => (s, vec![]),
+        };
+        let numbers = params
+            .iter()
+            .map(|p| p.parse::<f64>().map_err(|_| format!("`{}` is not a number", p)))
+            .collect::<Result<Vec<f64>, String>>()?;
+        // Single optional parameter
+        let number = |default: f64| match numbers[..] {
+            [] => Ok(default),
+            [n] => Ok(n),
+            _ => Err(format!("`{}` takes a single value", name)),
+        };
+        match name {
+            "none" if params.is_empty() => Ok(FilterSpec::None),
+            "srgb" if params.is_empty() => Ok(FilterSpec::Srgb),
+            "gamma" => match number(0.5f64)? {
+                y if y > 0f64 => Ok(FilterSpec::Gamma(y)),
+                _ => Err("the gamma exponent must be positive".to_string()),
+            },
+            "reinhard" => Ok(FilterSpec::Reinhard(number(0f64)?)),
+            "reinhard-extended" => match numbers[..] {
+                [white] | [white, _] if white <= 0f64 => Err("the white point must be positive".to_string()),
+                [white] => Ok(FilterSpec::ReinhardExtended { white, exposure: 0f64 }),
+                [white, exposure] => Ok(FilterSpec::ReinhardExtended { white, exposure }),
+                _ => Err("`reinhard-extended` takes a white point and an optional exposure".to_string()),
+            },
+            "hable" => Ok(FilterSpec::Hable(number(0f64)?)),
+            "aces" => Ok(FilterSpec::Aces(number(0f64)?)),
+            _ => Err(format!("unknown filter `{}`, expected {}", s, FILTERS)),
+        }
+    }
+}
+
+impl Cli {
+    /// Applies the command line overrides to the scene before it is built, the image size decides the camera
+    /// aspect ratio so it has to be known up front
+    pub fn apply_to_scene(&self, description: &mut SceneDescription) {
+        let image = &mut description.image;
+        let aspect_ratio = image.width as f64 / image.height as f64;
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => {
+                image.width = width;
+                image.height = height;
+            }
+            (Some(width), None) => {
+                image.width = width;
+                image.height = ((width as f64 / aspect_ratio) as u32).max(1);
+            }
+            (None, Some(height)) => {
+                image.width = ((height as f64 * aspect_ratio) as u32).max(1);
+                image.height = height;
+            }
+            (None, None) => {}
+        }
+        if let Some(spp) = self.spp {
+            image.samples_per_pixel = spp as usize;
+        }
+        if let Some(depth) = self.depth {
+            image.max_depth = depth as usize;
+        }
+        if self.seed.is_some() {
+            image.seed = self.seed;
+        }
+    }
+
+    /// Applies the command line settings that are not part of the scene
+    pub fn apply_to_settings(&self, settings: &mut RenderSettings) {
+        if let Some(threads) = self.threads {
+            settings.threads = threads as usize;
+        }
+        if let Some(sampler) = self.sampler {
+            settings.sampler = sampler;
+        }
+        if let Some(filter) = self.pixel_filter {
+            settings.filter = filter;
+        }
+        if let Some(light_sampling) = self.light_sampling {
+            settings.light_sampling = light_sampling;
+        }
+        settings.output = self.output.clone();
+    }
+
+    /// Checks the combinations of arguments that clap cannot
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.preview {
+            let extension = self
+                .output
+                .extension()
+                .map(|e| e.to_string_lossy().to_ascii_lowercase());
+            match (self.format, extension) {
+                (None, _) if OutputFormat::from_extension(&self.output).is_none() => {
+                    return Err(format!(
+                        "cannot tell the image format from `{}`, use a known extension or --format",
+                        self.output.display()
+                    ))
+                }
+                // A file without an extension can be in any format, otherwise they have to agree
+                (Some(format), Some(extension)) if extension != format.extension() => {
+                    return Err(format!(
+                        "--format {:?} does not match the extension of `{}`",
+                        format,
+                        self.output.display()
+                    ))
+                }
+                _ => {}
+            }
+        }
+        if self.filters.len() > 1 && self.filters.contains(&FilterSpec::None) {
+            return Err("--filter none cannot be combined with other filters".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        self.format
+            .or_else(|| OutputFormat::from_extension(&self.output))
+            .unwrap_or(OutputFormat::Ppm)
+    }
+
+    /// The post-processing filters, in the order they are applied. High dynamic range outputs are left linear unless
+    /// asked otherwise.
+    pub fn filter_chain(&self) -> Vec<Box<dyn Filter>> {
+        let default = if ImageFormat::from(self.output_format()).is_hdr() {
+            vec![]
+        } else {
+            vec![FilterSpec::Srgb]
+        };
+        let filters = if self.filters.is_empty() {
+            &default
+        } else {
+            &self.filters
+        };
+        filters
+            .iter()
+            .filter_map(|spec| match *spec {
+                FilterSpec::None => None,
+                // Gamma correction 0->1 brighten image, gamma compression
+                // Gamma correction > 1 -> darken image, gamma expansion
+                FilterSpec::Gamma(y) => Some(Box::new(GammaCorrection::new(y)) as Box<dyn Filter>),
+                FilterSpec::Srgb => Some(Box::new(SrgbEncode)),
+                FilterSpec::Reinhard(exposure) => Some(Box::new(Reinhard::new(exposure))),
+                FilterSpec::ReinhardExtended { white, exposure } => {
+                    Some(Box::new(Reinhard::with_white_point(exposure, white)))
+                }
+                FilterSpec::Hable(exposure) => Some(Box::new(Hable::new(exposure))),
+                FilterSpec::Aces(exposure) => Some(Box::new(AcesFitted::new(exposure))),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        let cli = Cli::try_parse_from(std::iter::once("rusttracing").chain(args.iter().copied()))
+            .map_err(|e| e.to_string())?;
+        cli.validate()?;
+        Ok(cli)
+    }
+
+    #[test]
+    fn test_cli_defaults() {
+        let cli = parse(&[]).unwrap();
+        assert_eq!(cli.scene, None);
+        assert_eq!(cli.output, PathBuf::from("render.ppm"));
+        assert_eq!(cli.output_format(), OutputFormat::Ppm);
+        assert!(cli.filters.is_empty());
+        assert_eq!(cli.filter_chain().len(), 1);
+        assert!(!cli.preview);
+
+        // High dynamic range files are written linear
+        let cli = parse(&["-o", "render.exr"]).unwrap();
+        assert_eq!(cli.output_format(), OutputFormat::Exr);
+        assert!(cli.filter_chain().is_empty());
+        let cli = parse(&["-o", "render.hdr", "--filter", "gamma"]).unwrap();
+        assert_eq!(cli.filter_chain().len(), 1);
+    }
+
+    #[test]
+    fn test_cli_overrides() {
+        let args = ["scene.toml", "--width", "800", "-s", "10", "-j", "3", "--filter", "gamma=0.4", "--pixel-filter", "lanczos=2", "--sampler", "halton", "--light-sampling", "lights"];
+        let cli = parse(&args).unwrap();
+        let mut description = SceneDescription::from_toml(include_str!("../scenes/default.toml")).unwrap();
+        cli.apply_to_scene(&mut description);
+        assert_eq!((description.image.width, description.image.height), (800, 450));
+        assert_eq!(description.image.samples_per_pixel, 10);
+        let mut settings = description.build().settings;
+        cli.apply_to_settings(&mut settings);
+        assert_eq!(settings.threads, 3);
+        assert_eq!(settings.filter, ReconstructionFilter::Lanczos(2f64));
+        assert_eq!(settings.sampler, SamplerKind::Halton);
+        assert_eq!(settings.light_sampling, LightSampling::Lights);
+        assert_eq!(cli.filters, vec![FilterSpec::Gamma(0.4)]);
+
+        let cli = parse(&["--filter", "reinhard-extended=4,-1", "--filter", "aces=1.5", "--filter", "gamma"]).unwrap();
+        assert_eq!(
+            cli.filters,
+            vec![
+                FilterSpec::ReinhardExtended {
+                    white: 4f64,
+                    exposure: -1f64
+                },
+                FilterSpec::Aces(1.5),
+                FilterSpec::Gamma(0.5)
+            ]
+        );
+        assert_eq!(cli.filter_chain().len(), 3);
+    }
+
+    #[test]
+    fn test_cli_invalid_combinations() {
+        assert!(parse(&["--preview", "--output", "x.ppm"]).is_err());
+        assert!(parse(&["--output", "render.png", "--format", "ppm"]).is_err());
+        assert!(parse(&["--output", "render.jpg", "--format", "png"]).is_err());
+        assert_eq!(parse(&["--output", "render.png"]).unwrap().output_format(), OutputFormat::Png);
+        assert!(parse(&["--output", "render.EXR", "--format", "exr-half"]).is_ok());
+        assert!(parse(&["--output", "render.hdr", "--format", "exr"]).is_err());
+        assert!(parse(&["--output", "render"]).is_err());
+        assert!(parse(&["--output", "render", "--format", "ppm"]).is_ok());
+        assert!(parse(&["--threads", "0"]).is_err());
+        assert!(parse(&["--depth", "0"]).is_err());
+        assert!(parse(&["--spp", "0"]).is_err());
+        assert!(parse(&["--filter", "sepia"]).is_err());
+        assert!(parse(&["--filter", "gamma=1,2"]).is_err());
+        assert!(parse(&["--filter", "srgb=2"]).is_err());
+        assert!(parse(&["--pixel-filter", "gaussian=-1"]).is_err());
+        assert!(parse(&["--pixel-filter", "sinc"]).is_err());
+        assert!(parse(&["--sampler", "random"]).is_err());
+        assert!(parse(&["--light-sampling", "both"]).is_err());
+        assert!(parse(&["--filter", "reinhard-extended"]).is_err());
+        assert!(parse(&["--filter", "reinhard-extended=-1"]).is_err());
+        assert!(parse(&["--filter", "none", "--filter", "gamma"]).is_err());
+        assert!(parse(&["--filter", "none"]).is_ok());
+    }
+}
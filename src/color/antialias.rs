@@ -1,35 +1,210 @@
-use crate::color::rgb::RgbColor;
-use crate::color::filter::Filter;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
 
-pub struct AntialiasingFilter {
-    pixels_per_chunk: u32,
-    image_width: u32
+/// Pixel reconstruction filter, weighing the samples of a render by their distance to the pixel centres
+///
+/// Every sample lands at a point of the film and counts towards all the pixels whose centre is closer than the
+/// radius of the filter, along both axes. A pixel is the weighted average of those samples. The filters are
+/// separable, the weight of a sample being the product of the filter at its horizontal and vertical offsets.
+///
+/// The box filter with a radius of half a pixel is the plain average of the samples in the pixel. Wider filters
+/// blur a little more but alias less, Mitchell-Netravali and Lanczos have negative lobes that keep edges sharp at
+/// the cost of some ringing.
+///
+/// # Examples
+/// ```
+/// # use rusttracing::color::antialias::ReconstructionFilter;
+/// let filter: ReconstructionFilter = "gaussian=2".parse().unwrap();
+/// assert_eq!(filter, ReconstructionFilter::Gaussian(2f64));
+/// assert_eq!(filter.weight(0f64, 2f64), 0f64);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReconstructionFilter {
+    /// Every sample within the radius weighs the same
+    Box(f64),
+    /// Weight falling linearly to 0 at the radius
+    Tent(f64),
+    /// Gaussian with a standard deviation of a third of the radius, shifted down to reach 0 at the radius
+    Gaussian(f64),
+    /// Mitchell-Netravali cubic with B = C = 1/3, stretched over the radius
+    Mitchell(f64),
+    /// Sinc windowed by a wider sinc, with as many lobes as the radius
+    Lanczos(f64),
 }
 
-impl AntialiasingFilter {
-    pub fn new(pixels_per_chunk: u32) -> Self {
-        AntialiasingFilter {
-            pixels_per_chunk,
-            image_width: 400
+impl Default for ReconstructionFilter {
+    fn default() -> Self {
+        ReconstructionFilter::Box(0.5)
+    }
+}
+
+impl ReconstructionFilter {
+    /// Distance from the pixel centre beyond which samples do not count, in pixels
+    pub fn radius(&self) -> f64 {
+        match *self {
+            ReconstructionFilter::Box(r)
+            | ReconstructionFilter::Tent(r)
+            | ReconstructionFilter::Gaussian(r)
+            | ReconstructionFilter::Mitchell(r)
+            | ReconstructionFilter::Lanczos(r) => r,
+        }
+    }
+
+    /// Weight of a sample at offset (`dx`, `dy`) from a pixel centre, in pixels
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate(dx) * self.evaluate(dy)
+    }
+
+    // One dimensional filter
+    fn evaluate(&self, x: f64) -> f64 {
+        let r = self.radius();
+        // Half open so that a sample on the border between two pixels only goes to one of them
+        if x < -r || x >= r {
+            return 0f64;
+        }
+        match *self {
+            ReconstructionFilter::Box(_) => 1f64,
+            ReconstructionFilter::Tent(_) => 1f64 - x.abs() / r,
+            ReconstructionFilter::Gaussian(_) => {
+                let sigma = r / 3f64;
+                let gaussian = |x: f64| (-x * x / (2f64 * sigma * sigma)).exp();
+                (gaussian(x) - gaussian(r)).max(0f64)
+            }
+            ReconstructionFilter::Mitchell(_) => mitchell(2f64 * x / r),
+            ReconstructionFilter::Lanczos(_) => sinc(x) * sinc(x / r),
+        }
+    }
+}
+
+// Mitchell-Netravali cubic on [-2, 2] with B = C = 1/3
+fn mitchell(x: f64) -> f64 {
+    let (b, c) = (1f64 / 3f64, 1f64 / 3f64);
+    let x = x.abs();
+    if x < 1f64 {
+        ((12f64 - 9f64 * b - 6f64 * c) * x.powi(3) + (-18f64 + 12f64 * b + 6f64 * c) * x.powi(2) + (6f64 - 2f64 * b))
+            / 6f64
+    } else if x < 2f64 {
+        ((-b - 6f64 * c) * x.powi(3)
+            + (6f64 * b + 30f64 * c) * x.powi(2)
+            + (-12f64 * b - 48f64 * c) * x
+            + (8f64 * b + 24f64 * c))
+            / 6f64
+    } else {
+        0f64
+    }
+}
+
+// Normalised sinc, sin(pi x) / (pi x)
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 {
+        1f64
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl fmt::Display for ReconstructionFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReconstructionFilter::Box(_) => "box",
+            ReconstructionFilter::Tent(_) => "tent",
+            ReconstructionFilter::Gaussian(_) => "gaussian",
+            ReconstructionFilter::Mitchell(_) => "mitchell",
+            ReconstructionFilter::Lanczos(_) => "lanczos",
+        };
+        write!(f, "{}={}", name, self.radius())
+    }
+}
+
+impl FromStr for ReconstructionFilter {
+    type Err = String;
+
+    /// Parses `name[=radius]`, the radius defaulting to the usual one for each filter
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, radius) = match s.split_once('=') {
+            Some((name, radius)) => (
+                name,
+                Some(
+                    radius
+                        .parse::<f64>()
+                        .map_err(|_| format!("`{}` is not a number", radius))?,
+                ),
+            ),
+            None => (s, None),
+        };
+        let filter = match name {
+            "box" => ReconstructionFilter::Box(radius.unwrap_or(0.5)),
+            "tent" => ReconstructionFilter::Tent(radius.unwrap_or(1f64)),
+            "gaussian" => ReconstructionFilter::Gaussian(radius.unwrap_or(1.5)),
+            "mitchell" => ReconstructionFilter::Mitchell(radius.unwrap_or(2f64)),
+            "lanczos" => ReconstructionFilter::Lanczos(radius.unwrap_or(3f64)),
+            _ => {
+                return Err(format!(
+                    "unknown pixel filter `{}`, expected box, tent, gaussian, mitchell or lanczos",
+                    name
+                ))
+            }
+        };
+        if !(filter.radius() > 0f64 && filter.radius().is_finite()) {
+            return Err("the filter radius must be positive".to_string());
         }
+        Ok(filter)
     }
 }
 
-impl Filter for AntialiasingFilter {
-    fn apply_filter(&self, render: &mut Vec<RgbColor>) {
-        // render.windows(4)
-        // .zip(render)
-        // .inspect(|p| eprintln!("{:?}", p))
-        // .collect::<Vec<_>>();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // for chunk in render.windows(self.pixels_per_chunk as usize) {
+    const FILTERS: [ReconstructionFilter; 5] = [
+        ReconstructionFilter::Box(0.5),
+        ReconstructionFilter::Tent(1f64),
+        ReconstructionFilter::Gaussian(1.5),
+        ReconstructionFilter::Mitchell(2f64),
+        ReconstructionFilter::Lanczos(3f64),
+    ];
 
-        // }
+    #[test]
+    fn test_filters_vanish_outside_radius() {
+        for filter in FILTERS {
+            let r = filter.radius();
+            assert!(filter.weight(0f64, 0f64) > 0f64, "{}", filter);
+            assert_eq!(filter.weight(r, 0f64), 0f64, "{}", filter);
+            assert_eq!(filter.weight(0f64, -r - 0.1), 0f64, "{}", filter);
+            // Symmetric, apart from the border itself
+            for i in 1..20 {
+                let x = r * i as f64 / 20f64;
+                assert!((filter.weight(x, 0f64) - filter.weight(-x, 0f64)).abs() < 1e-12, "{}", filter);
+            }
+        }
+    }
 
-        // for pixel in render.step_ {
-        //     for iter in 0..self.samples_per_pixel {
+    #[test]
+    fn test_filter_reference_values() {
+        assert_eq!(ReconstructionFilter::Box(0.5).weight(-0.5, 0.49), 1f64);
+        assert_eq!(ReconstructionFilter::Tent(2f64).weight(1f64, 0.5), 0.5 * 0.75);
+        // Mitchell-Netravali is 8/9 at its centre and 1/18 one unit away
+        assert!((ReconstructionFilter::Mitchell(2f64).weight(0f64, 0f64) - 64f64 / 81f64).abs() < 1e-12);
+        assert!((ReconstructionFilter::Mitchell(2f64).weight(1f64, 0f64) - 8f64 / 9f64 / 18f64).abs() < 1e-12);
+        // Lanczos goes through 0 at every integer and is negative in between the first two
+        let lanczos = ReconstructionFilter::Lanczos(3f64);
+        assert!(lanczos.weight(1f64, 0f64).abs() < 1e-12);
+        assert!(lanczos.weight(1.5, 0f64) < 0f64);
+        let gaussian = ReconstructionFilter::Gaussian(1.5);
+        assert!((gaussian.weight(0.5, 0f64) - ((-0.5f64).exp() - (-4.5f64).exp()) * (1f64 - (-4.5f64).exp())).abs() < 1e-12);
+    }
 
-        //     }
-        // }
+    #[test]
+    fn test_parse_filters() {
+        assert_eq!("box".parse(), Ok(ReconstructionFilter::Box(0.5)));
+        assert_eq!("tent=1.5".parse(), Ok(ReconstructionFilter::Tent(1.5)));
+        assert_eq!("lanczos".parse(), Ok(ReconstructionFilter::Lanczos(3f64)));
+        for filter in FILTERS {
+            assert_eq!(filter.to_string().parse(), Ok(filter));
+        }
+        assert!("mitchell=0".parse::<ReconstructionFilter>().is_err());
+        assert!("tent=wide".parse::<ReconstructionFilter>().is_err());
+        assert!("sinc".parse::<ReconstructionFilter>().is_err());
     }
-}
\ No newline at end of file
+}
@@ -1,34 +1,82 @@
 use crate::color::rgb::RgbColor;
 use crate::color::filter::Filter;
 
+/// Power law gamma, every channel c becomes c^y
+///
+/// Exponents below 1 brighten the image (gamma compression, encoding linear values for display), above 1 darken
+/// it (gamma expansion, decoding back to linear). Negative channels are clipped to 0.
 pub struct GammaCorrection {
-    n: f64
+    y: f64
 }
 
 impl GammaCorrection {
     pub fn new(y: f64) -> Self {
-        GammaCorrection {
-            n: 1f64 / y as f64
+        GammaCorrection { y }
+    }
+}
+
+impl Filter for GammaCorrection {
+    fn apply_filter(&self, render: &mut Vec<RgbColor>) {
+        for pixel in render.iter_mut() {
+            pixel.x = pixel.x.max(0f64).powf(self.y);
+            pixel.y = pixel.y.max(0f64).powf(self.y);
+            pixel.z = pixel.z.max(0f64).powf(self.y);
         }
     }
+}
+
+// sRGB transfer functions (IEC 61966-2-1)
+// A linear segment near black, to avoid the infinite slope of a pure power law at 0, joined to a 2.4 power curve
+// offset so that the two meet with the same value and slope. Overall close to a 2.2 gamma.
 
-    // calculate nth root of value using newton's method
-    fn nth_root_calc(&self, value: f64) -> f64 {
-        let Ai = 1f64 - 1f64 / self.n as f64;
-        let Bi = value / self.n as f64;
-        
-        // let x0 = 1;
-        let x1 = Ai + Bi; // 1st iteration
-        Ai*x1 + Bi*x1.powf(1f64 - self.n) // 2nd iteration
+/// sRGB OETF, encodes a linear value for display
+pub fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1f64 / 2.4) - 0.055
     }
 }
 
-impl Filter for GammaCorrection {
+/// sRGB EOTF, decodes a displayed value back to linear light
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl RgbColor {
+    /// sRGB encoded colour of a linear colour
+    pub fn to_srgb(self) -> RgbColor {
+        RgbColor::new(linear_to_srgb(self.x), linear_to_srgb(self.y), linear_to_srgb(self.z))
+    }
+
+    /// Linear colour of an sRGB encoded colour, e.g. from a texture or a colour picker
+    pub fn to_linear(self) -> RgbColor {
+        RgbColor::new(srgb_to_linear(self.x), srgb_to_linear(self.y), srgb_to_linear(self.z))
+    }
+}
+
+/// Encodes a linear render to sRGB, the last filter before writing an 8-bit image
+pub struct SrgbEncode;
+
+impl Filter for SrgbEncode {
     fn apply_filter(&self, render: &mut Vec<RgbColor>) {
-        for iter in 0..render.len() {
-            render[iter].x = self.nth_root_calc(render[iter].x);
-            render[iter].y = self.nth_root_calc(render[iter].y);
-            render[iter].z = self.nth_root_calc(render[iter].z);
+        for pixel in render.iter_mut() {
+            *pixel = pixel.to_srgb();
+        }
+    }
+}
+
+/// Decodes an sRGB image back to linear light
+pub struct SrgbDecode;
+
+impl Filter for SrgbDecode {
+    fn apply_filter(&self, render: &mut Vec<RgbColor>) {
+        for pixel in render.iter_mut() {
+            *pixel = pixel.to_linear();
         }
     }
 }
@@ -36,8 +84,57 @@ impl Filter for GammaCorrection {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-12, "{} != {}", a, b);
+    }
+
     #[test]
-    fn name() {
-        unimplemented!();
+    fn test_gamma_reference_values() {
+        let mut render = vec![
+            RgbColor::new(0.25, 0.5, 1f64),
+            RgbColor::new(0f64, 0.01, 4f64),
+        ];
+        GammaCorrection::new(0.5f64).apply_filter(&mut render);
+        assert_close(render[0].x, 0.5);
+        assert_close(render[0].y, std::f64::consts::FRAC_1_SQRT_2);
+        assert_close(render[0].z, 1f64);
+        assert_close(render[1].x, 0f64);
+        assert_close(render[1].y, 0.1);
+        assert_close(render[1].z, 2f64);
+
+        let mut render = vec![RgbColor::new(0.5, 0.5, 0.5)];
+        GammaCorrection::new(1f64 / 2.2).apply_filter(&mut render);
+        assert_close(render[0].x, 0.7297400528407231);
+    }
+
+    #[test]
+    fn test_srgb_reference_values() {
+        // Power segment
+        assert_close(linear_to_srgb(0.5), 0.7353569830524495);
+        assert_close(linear_to_srgb(0.18), 0.46135612950044164);
+        assert_close(srgb_to_linear(0.5), 0.21404114048223255);
+        assert_close(srgb_to_linear(200f64 / 255f64), 0.5775804404296506);
+        // Linear segment
+        assert_close(linear_to_srgb(0.001), 0.01292);
+        assert_close(srgb_to_linear(0.02), 0.0015479876160990713);
+        // End points, and the two segments meet, to the precision of the rounded constants of the standard
+        assert_close(linear_to_srgb(0f64), 0f64);
+        assert_close(linear_to_srgb(1f64), 1f64);
+        assert!((linear_to_srgb(0.0031308) - linear_to_srgb(0.0031308 + 1e-12)).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_srgb_filters_round_trip() {
+        let original: Vec<RgbColor> = (0..=100)
+            .map(|i| RgbColor::new(i as f64 / 100f64, (i as f64 / 100f64).powi(3), 0.002))
+            .collect();
+        let mut render = original.clone();
+        SrgbEncode.apply_filter(&mut render);
+        assert!(render.iter().zip(&original).all(|(encoded, linear)| encoded.x >= linear.x - 1e-12));
+        SrgbDecode.apply_filter(&mut render);
+        for (pixel, expected) in render.iter().zip(&original) {
+            assert!((*pixel - *expected).len() < 1e-12);
+        }
     }
 }
@@ -1,4 +1,5 @@
 pub mod rgb;
 pub mod filter;
 pub mod antialias;
-pub mod gamma;
\ No newline at end of file
+pub mod gamma;
+pub mod tonemap;
\ No newline at end of file
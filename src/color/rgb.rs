@@ -2,14 +2,33 @@ pub use crate::geometry::vec3::Vector as RgbColor;
 
 impl std::fmt::Display for RgbColor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // write!(f, "#{:02x}{:02x}{:02x}", (255.999*self.x) as u64, (255.999*self.y) as u64, (255.999*self.z) as u64)
-        write!(f, "{} {} {}", (255.999*self.x) as u64, (255.999*self.y) as u64, (255.999*self.z) as u64)
+        let [r, g, b] = self.to_rgb8();
+        write!(f, "{} {} {}", r, g, b)
     }
 }
 
 impl RgbColor {
+    /// 8-bit channels for low dynamic range images, anything outside [0, 1] is clipped
+    pub fn to_rgb8(self) -> [u8; 3] {
+        [self.x, self.y, self.z].map(|c| (255.999 * c.clamp(0f64, 1f64)) as u8)
+    }
+
     pub fn hex(self) -> String {
-        format!("#{:02x}{:02x}{:02x}", (255.999*self.x) as u64, (255.999*self.y) as u64, (255.999*self.z) as u64)
+        let [r, g, b] = self.to_rgb8();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_8_bit_conversions_clip() {
+        let color = RgbColor::new(1.5, -0.2, 0.5);
+        assert_eq!(color.to_rgb8(), [255, 0, 127]);
+        assert_eq!(color.to_string(), "255 0 127");
+        assert_eq!(color.hex(), "#ff007f");
+    }
+}
@@ -0,0 +1,192 @@
+use crate::color::filter::Filter;
+use crate::color::rgb::RgbColor;
+
+// Tone mapping
+// Renders hold radiance, which has no upper bound, while displays top out at 1. Clipping turns everything brighter
+// than 1 to flat white. Tone mapping operators instead compress the whole range into [0, 1), keeping the darks
+// nearly linear and rolling the highlights off smoothly. All the operators here work on each channel separately and
+// scale the input by 2^exposure first, exposure being in stops.
+//
+// They expect and produce linear values, gamma or sRGB encoding is a separate filter applied after them.
+
+fn exposure_scale(exposure: f64) -> f64 {
+    2f64.powf(exposure)
+}
+
+fn map_channels(render: &mut [RgbColor], scale: f64, curve: impl Fn(f64) -> f64) {
+    for pixel in render.iter_mut() {
+        pixel.x = curve(pixel.x.max(0f64) * scale);
+        pixel.y = curve(pixel.y.max(0f64) * scale);
+        pixel.z = curve(pixel.z.max(0f64) * scale);
+    }
+}
+
+/// Reinhard's operator, x / (1 + x), optionally extended with a white point
+///
+/// The simple form never quite reaches white. The extended form
+/// x (1 + x / white^2) / (1 + x)
+/// maps `white` to exactly 1 and is linear for small values, everything brighter than the white point is clipped.
+pub struct Reinhard {
+    scale: f64,
+    white: Option<f64>,
+}
+
+impl Reinhard {
+    pub fn new(exposure: f64) -> Self {
+        Reinhard {
+            scale: exposure_scale(exposure),
+            white: None,
+        }
+    }
+
+    /// Extended operator, `white` is the smallest exposed value mapped to white
+    pub fn with_white_point(exposure: f64, white: f64) -> Self {
+        Reinhard {
+            scale: exposure_scale(exposure),
+            white: Some(white),
+        }
+    }
+}
+
+impl Filter for Reinhard {
+    fn apply_filter(&self, render: &mut Vec<RgbColor>) {
+        match self.white {
+            None => map_channels(render, self.scale, |x| x / (1f64 + x)),
+            Some(white) => {
+                let white_sq = white * white;
+                map_channels(render, self.scale, |x| (x * (1f64 + x / white_sq) / (1f64 + x)).min(1f64))
+            }
+        }
+    }
+}
+
+/// John Hable's filmic curve from Uncharted 2
+///
+/// A rational curve with a toe for the shadows and a shoulder for the highlights, normalised so that the linear
+/// white point of 11.2 maps to 1.
+pub struct Hable {
+    scale: f64,
+}
+
+impl Hable {
+    const WHITE: f64 = 11.2;
+    // Exposure bias of the original, the curve is too dark at an exposure of 0 without it
+    const EXPOSURE_BIAS: f64 = 2f64;
+
+    pub fn new(exposure: f64) -> Self {
+        Hable {
+            scale: exposure_scale(exposure) * Self::EXPOSURE_BIAS,
+        }
+    }
+
+    // Shoulder strength A, linear strength B, linear angle C, toe strength D, toe numerator E and denominator F
+    fn partial(x: f64) -> f64 {
+        let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+        ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+    }
+}
+
+impl Filter for Hable {
+    fn apply_filter(&self, render: &mut Vec<RgbColor>) {
+        let white_scale = 1f64 / Hable::partial(Hable::WHITE);
+        map_channels(render, self.scale, |x| (Hable::partial(x) * white_scale).min(1f64))
+    }
+}
+
+/// Krzysztof Narkowicz's fit of the ACES reference rendering and output transforms
+///
+/// x (2.51 x + 0.03) / (x (2.43 x + 0.59) + 0.14)
+/// Quick and close to the full transform for the sRGB display it was fitted to, with its more saturated look.
+pub struct AcesFitted {
+    scale: f64,
+}
+
+impl AcesFitted {
+    // The fit includes the 0.6 exposure of the reference transform
+    const EXPOSURE_BIAS: f64 = 0.6;
+
+    pub fn new(exposure: f64) -> Self {
+        AcesFitted {
+            scale: exposure_scale(exposure) * Self::EXPOSURE_BIAS,
+        }
+    }
+}
+
+impl Filter for AcesFitted {
+    fn apply_filter(&self, render: &mut Vec<RgbColor>) {
+        map_channels(render, self.scale, |x| {
+            ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0f64, 1f64)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(filter: &dyn Filter, values: &[f64]) -> Vec<f64> {
+        let mut render: Vec<RgbColor> = values.iter().map(|&v| RgbColor::new(v, v, v)).collect();
+        filter.apply_filter(&mut render);
+        render.iter().map(|pixel| pixel.x).collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_reinhard() {
+        assert_close(&map(&Reinhard::new(0f64), &[0f64, 1f64, 3f64]), &[0f64, 0.5, 0.75]);
+        // One stop up doubles the input
+        assert_close(&map(&Reinhard::new(1f64), &[0.5, 1.5]), &[0.5, 0.75]);
+        // The white point maps to white, anything brighter is clipped
+        assert_close(
+            &map(&Reinhard::with_white_point(0f64, 4f64), &[1f64, 4f64, 100f64]),
+            &[17f64 / 32f64, 1f64, 1f64],
+        );
+    }
+
+    #[test]
+    fn test_hable() {
+        let mapped = map(&Hable::new(0f64), &[0f64, 0.5, 1f64, 5.6, 100f64]);
+        assert!(mapped[0].abs() < 1e-9);
+        // Exactly white at the white point after the exposure bias
+        assert_close(&mapped[3..4], &[1f64]);
+        assert_eq!(mapped[4], 1f64);
+        assert!(mapped.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_aces_fitted() {
+        // Reference values of the fitted curve, after the 0.6 exposure bias
+        let expected = |x: f64| {
+            let x = x * 0.6;
+            (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+        };
+        assert_close(
+            &map(&AcesFitted::new(0f64), &[0f64, 0.18, 1f64]),
+            &[0f64, expected(0.18), expected(1f64)],
+        );
+        let bright = map(&AcesFitted::new(0f64), &[1000f64]);
+        assert!(bright[0] > 0.99 && bright[0] <= 1f64);
+        assert_close(&map(&AcesFitted::new(-1f64), &[2f64]), &map(&AcesFitted::new(0f64), &[1f64]));
+    }
+
+    #[test]
+    fn test_tone_mapping_is_monotonic_and_bounded() {
+        let values: Vec<f64> = (0..200).map(|i| (i as f64 / 10f64).powi(2)).collect();
+        let filters: [Box<dyn Filter>; 4] = [
+            Box::new(Reinhard::new(0f64)),
+            Box::new(Reinhard::with_white_point(0f64, 8f64)),
+            Box::new(Hable::new(0f64)),
+            Box::new(AcesFitted::new(0f64)),
+        ];
+        for filter in filters.iter() {
+            let mapped = map(filter.as_ref(), &values);
+            assert!(mapped.windows(2).all(|w| w[0] <= w[1]));
+            assert!(mapped.iter().all(|&v| (0f64..=1f64).contains(&v)));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::geometry::vec3::Vector as Point;
+use crate::geometry::vec3::Vector;
+use crate::scene::ray::Ray;
+
+/// Axis aligned bounding box, the cheapest volume to test a ray against. Every object in the world reports one so
+/// that whole groups of objects can be skipped when a ray misses their box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Aabb {
+    pub fn new(min: Point, max: Point) -> Self {
+        Aabb { min, max }
+    }
+
+    /// Box that contains nothing, any box merged with it is left unchanged
+    pub fn empty() -> Self {
+        Aabb {
+            min: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    /// Smallest box containing both input boxes
+    pub fn union(a: Aabb, b: Aabb) -> Aabb {
+        Aabb {
+            min: Point::new(a.min.x.min(b.min.x), a.min.y.min(b.min.y), a.min.z.min(b.min.z)),
+            max: Point::new(a.max.x.max(b.max.x), a.max.y.max(b.max.y), a.max.z.max(b.max.z)),
+        }
+    }
+
+    /// Smallest box containing the input box and point
+    pub fn grow(a: Aabb, p: Point) -> Aabb {
+        Aabb::union(a, Aabb::new(p, p))
+    }
+
+    pub fn centroid(&self) -> Point {
+        (self.min + self.max) * 0.5f64
+    }
+
+    pub fn extent(&self) -> Vector {
+        self.max - self.min
+    }
+
+    /// Surface area of the box, the probability of a random ray hitting it is proportional to this
+    pub fn surface_area(&self) -> f64 {
+        let e = self.extent();
+        if e.x < 0f64 || e.y < 0f64 || e.z < 0f64 {
+            return 0f64;
+        }
+        2f64 * (e.x * e.y + e.y * e.z + e.z * e.x)
+    }
+
+    /// Index of the axis along which the box is the longest, 0 for x, 1 for y and 2 for z
+    pub fn longest_axis(&self) -> usize {
+        let e = self.extent();
+        if e.x >= e.y && e.x >= e.z {
+            0
+        } else if e.y >= e.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    // Slab test
+    // The box is the intersection of three slabs, one per axis, bounded by the planes min and max.
+    // A ray enters the slab of an axis at t = (min - O) / D and leaves it at t = (max - O) / D, the ray hits the
+    // box if the last entry happens before the first exit.
+    // `inv_direction` is 1 / D, precomputed once per ray since it is the same for every box
+    pub fn hit(&self, ray: &Ray, inv_direction: &Vector, range: (f64, f64)) -> bool {
+        let (mut t_min, mut t_max) = range;
+        for axis in 0..3 {
+            let (origin, inv_d, min, max) = match axis {
+                0 => (ray.origin.x, inv_direction.x, self.min.x, self.max.x),
+                1 => (ray.origin.y, inv_direction.y, self.min.y, self.max.y),
+                _ => (ray.origin.z, inv_direction.z, self.min.z, self.max.z),
+            };
+            let mut t0 = (min - origin) * inv_d;
+            let mut t1 = (max - origin) * inv_d;
+            if inv_d < 0f64 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // f64::max and f64::min ignore the NaN produced by a ray lying on one of the slab planes
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Component of a vector along the given axis, 0 for x, 1 for y and 2 for z
+pub fn axis(v: Vector, axis: usize) -> f64 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inv(ray: &Ray) -> Vector {
+        Vector::new(1f64 / ray.direction.x, 1f64 / ray.direction.y, 1f64 / ray.direction.z)
+    }
+
+    #[test]
+    fn test_aabb_hit() {
+        let aabb = Aabb::new(Point::new(-1f64, -1f64, -3f64), Point::new(1f64, 1f64, -2f64));
+        let towards = Ray::new(Point::default(), Vector::new(0f64, 0f64, -1f64));
+        let away = Ray::new(Point::default(), Vector::new(0f64, 0f64, 1f64));
+        let beside = Ray::new(Point::new(2f64, 0f64, 0f64), Vector::new(0f64, 0f64, -1f64));
+        assert!(aabb.hit(&towards, &inv(&towards), (0f64, f64::INFINITY)));
+        assert!(!aabb.hit(&towards, &inv(&towards), (0f64, 1f64)));
+        assert!(!aabb.hit(&away, &inv(&away), (0f64, f64::INFINITY)));
+        assert!(!aabb.hit(&beside, &inv(&beside), (0f64, f64::INFINITY)));
+    }
+
+    #[test]
+    fn test_aabb_union_and_area() {
+        let a = Aabb::new(Point::new(0f64, 0f64, 0f64), Point::new(1f64, 1f64, 1f64));
+        let b = Aabb::new(Point::new(1f64, 0f64, 0f64), Point::new(2f64, 1f64, 1f64));
+        let ab = Aabb::union(a, b);
+        assert_eq!(ab, Aabb::new(Point::new(0f64, 0f64, 0f64), Point::new(2f64, 1f64, 1f64)));
+        assert_eq!(ab.surface_area(), 10f64);
+        assert_eq!(ab.longest_axis(), 0);
+        assert_eq!(Aabb::union(Aabb::empty(), a), a);
+        assert_eq!(Aabb::empty().surface_area(), 0f64);
+    }
+}
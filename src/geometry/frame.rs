@@ -0,0 +1,71 @@
+use crate::geometry::vec3::Vector;
+
+/// Orthonormal basis around a normal, the local frame directions are sampled in
+///
+/// In local coordinates the normal is the z axis, so the cosine of the angle between a unit direction and the normal
+/// is simply its z component.
+///
+/// # Examples
+/// ```
+/// # use rusttracing::geometry::frame::Frame;
+/// # use rusttracing::geometry::vec3::Vector;
+/// let frame = Frame::from_normal(Vector::new(0.0, 1.0, 0.0));
+/// let local = frame.to_local(Vector::new(0.0, 1.0, 0.0));
+/// assert!((local.z - 1.0).abs() < 1e-12);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frame {
+    pub s: Vector,
+    pub t: Vector,
+    pub n: Vector,
+}
+
+impl Frame {
+    /// Frame with `n`, a unit vector, as its z axis. The other two axes are picked with the branchless construction
+    /// of Duff et al., Building an Orthonormal Basis, Revisited.
+    pub fn from_normal(n: Vector) -> Self {
+        let sign = 1f64.copysign(n.z);
+        let a = -1f64 / (sign + n.z);
+        let b = n.x * n.y * a;
+        Frame {
+            s: Vector::new(1f64 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+            t: Vector::new(b, sign + n.y * n.y * a, -n.y),
+            n,
+        }
+    }
+
+    /// World direction of a direction in the frame
+    pub fn to_world(&self, v: Vector) -> Vector {
+        self.s * v.x + self.t * v.y + self.n * v.z
+    }
+
+    /// Direction in the frame of a world direction
+    pub fn to_local(&self, v: Vector) -> Vector {
+        Vector::new(Vector::dot(v, self.s), Vector::dot(v, self.t), Vector::dot(v, self.n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_is_orthonormal() {
+        for n in [
+            Vector::new(0f64, 0f64, 1f64),
+            Vector::new(0f64, 0f64, -1f64),
+            Vector::unit(Vector::new(1f64, -2f64, 0.5f64)),
+            Vector::unit(Vector::new(-0.3f64, 0.1f64, -4f64)),
+        ] {
+            let frame = Frame::from_normal(n);
+            for (a, b) in [(frame.s, frame.t), (frame.t, frame.n), (frame.n, frame.s)] {
+                assert!(Vector::dot(a, b).abs() < 1e-12);
+                assert!((a.len() - 1f64).abs() < 1e-12);
+            }
+            // Right handed, and local and world coordinates round trip
+            assert!((Vector::cross(frame.s, frame.t) - frame.n).len() < 1e-12);
+            let v = Vector::new(0.2f64, -0.7f64, 0.4f64);
+            assert!((frame.to_local(frame.to_world(v)) - v).len() < 1e-12);
+        }
+    }
+}
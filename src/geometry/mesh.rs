@@ -0,0 +1,313 @@
+use crate::color::rgb::RgbColor;
+use crate::geometry::aabb::Aabb;
+use crate::geometry::triangle;
+use crate::geometry::vec3::Vector;
+use crate::geometry::vec3::Vector as Point;
+use crate::scene::bvh::Bvh;
+use crate::scene::intersect::Intersect;
+use crate::scene::intersect::Intersection;
+use crate::scene::intersect::SurfaceSample;
+use crate::scene::material::Material;
+use crate::scene::ray::Ray;
+use std::fmt::{Debug, Formatter, Result};
+use std::sync::Arc;
+
+/// Triangles sharing a single material, added to a world as one object
+///
+/// The vertices and their attributes are stored once and shared by the faces around them, each face only holds the
+/// indices of its three corners. Scans with millions of faces take a few tens of bytes per face besides their
+/// vertices.
+///
+/// The mesh keeps its own bounding volume hierarchy over its faces so that a world holding a few large meshes is as
+/// quick to trace as one holding every triangle directly. Models with several materials are loaded as one mesh per
+/// material.
+///
+/// # Examples
+/// ```
+/// # use rusttracing::color::rgb::RgbColor;
+/// # use rusttracing::geometry::mesh::Mesh;
+/// # use rusttracing::geometry::vec3::Vector;
+/// # use rusttracing::scene::intersect::Intersect;
+/// # use rusttracing::scene::material::matte::Matte;
+/// # use rusttracing::scene::ray::Ray;
+/// # use std::sync::Arc;
+/// // A unit square in the z = -1 plane, as two triangles sharing a diagonal
+/// let positions = vec![
+///     Vector::new(0f64, 0f64, -1f64),
+///     Vector::new(1f64, 0f64, -1f64),
+///     Vector::new(1f64, 1f64, -1f64),
+///     Vector::new(0f64, 1f64, -1f64),
+/// ];
+/// let material = Arc::new(Matte::new(RgbColor::new(0.5f64, 0.5f64, 0.5f64)));
+/// let mesh = Mesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], material);
+/// let ray = Ray::new(Vector::new(0.2f64, 0.7f64, 0f64), Vector::new(0f64, 0f64, -1f64));
+/// assert_eq!(mesh.intersects(&ray, (0.001, f64::INFINITY)).unwrap().t, 1f64);
+/// ```
+pub struct Mesh {
+    positions: Vec<Point>,
+    normals: Option<Vec<Vector>>,
+    uvs: Option<Vec<(f64, f64)>>,
+    colors: Option<Vec<RgbColor>>,
+    // Indices of the corners of every face into the vertex lists, counter-clockwise seen from the front
+    faces: Vec<[usize; 3]>,
+    bvh: Bvh,
+    bbox: Aabb,
+    // Running total of the areas of the faces, to pick them in proportion to their area
+    areas: Vec<f64>,
+    material: Arc<dyn Material>,
+}
+
+impl Mesh {
+    /// Builds a mesh of flat faces between the given vertices
+    ///
+    /// # Arguments
+    /// * `positions` - the vertices
+    /// * `faces` - the indices of the three vertices of every face
+    /// * `material` - the material of the whole mesh
+    ///
+    /// # Panics
+    /// If a face refers to a vertex past the end of `positions`
+    pub fn new(positions: Vec<Point>, faces: Vec<[usize; 3]>, material: Arc<dyn Material>) -> Self {
+        assert!(
+            faces.iter().flatten().all(|&i| i < positions.len()),
+            "a face refers to a vertex past the last of {}",
+            positions.len()
+        );
+        let corners = |face: &[usize; 3]| face.map(|i| positions[i]);
+        let areas = faces
+            .iter()
+            .scan(0f64, |total, face| {
+                *total += triangle::area(&corners(face));
+                Some(*total)
+            })
+            .collect();
+        let bbox = faces.iter().fold(Aabb::empty(), |b, face| {
+            Aabb::union(b, triangle::bounding_box(&corners(face)))
+        });
+        Mesh {
+            bvh: Bvh::from_boxes(faces.iter().map(|face| triangle::bounding_box(&corners(face)))),
+            positions,
+            normals: None,
+            uvs: None,
+            colors: None,
+            faces,
+            bbox,
+            areas,
+            material,
+        }
+    }
+
+    /// Shades the faces with normals interpolated between the given vertex normals, one per vertex
+    pub fn with_normals(self, normals: Vec<Vector>) -> Self {
+        assert_eq!(normals.len(), self.positions.len(), "one normal per vertex");
+        Mesh {
+            normals: Some(normals),
+            ..self
+        }
+    }
+
+    /// Texture coordinates of the vertices, without them the corners of every face sit at (0, 0), (1, 0) and (0, 1)
+    pub fn with_uvs(self, uvs: Vec<(f64, f64)>) -> Self {
+        assert_eq!(uvs.len(), self.positions.len(), "one texture coordinate per vertex");
+        Mesh { uvs: Some(uvs), ..self }
+    }
+
+    /// Colours of the vertices, interpolated across the faces and reported in the intersections
+    pub fn with_colors(self, colors: Vec<RgbColor>) -> Self {
+        assert_eq!(colors.len(), self.positions.len(), "one colour per vertex");
+        Mesh {
+            colors: Some(colors),
+            ..self
+        }
+    }
+
+    /// Number of triangles in the mesh
+    pub fn len(&self) -> usize {
+        self.faces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+
+    /// Total area of the triangles
+    pub fn area(&self) -> f64 {
+        self.areas.last().copied().unwrap_or(0f64)
+    }
+
+    // Area of the face at `index`
+    fn face_area(&self, index: usize) -> f64 {
+        self.areas[index] - if index > 0 { self.areas[index - 1] } else { 0f64 }
+    }
+
+    fn corners(&self, index: usize) -> [Point; 3] {
+        self.faces[index].map(|i| self.positions[i])
+    }
+
+    fn intersects_face(&self, index: usize, ray: &Ray, range: (f64, f64)) -> Option<Intersection> {
+        let corners = self.corners(index);
+        let hit = triangle::intersect(&corners, ray, range)?;
+        let face = self.faces[index];
+        Some(triangle::shade(
+            &corners,
+            ray,
+            hit,
+            self.normals.as_ref().map(|normals| face.map(|i| normals[i])),
+            self.uvs.as_ref().map(|uvs| face.map(|i| uvs[i])),
+            self.colors.as_ref().map(|colors| face.map(|i| colors[i])),
+        ))
+    }
+}
+
+impl Intersect for Mesh {
+    fn intersects(&self, ray: &Ray, range: (f64, f64)) -> Option<Intersection> {
+        self.bvh
+            .hit_with(ray, range, |index, range| self.intersects_face(index, ray, range))
+            .map(|(intersection, _)| intersection)
+    }
+
+    fn occludes(&self, ray: &Ray, range: (f64, f64)) -> bool {
+        self.bvh
+            .any_hit_with(ray, range, |index| triangle::intersect(&self.corners(index), ray, range).is_some())
+    }
+
+    fn material(&self) -> &dyn Material {
+        &*self.material
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Uniform by area over the whole mesh: a face is picked in proportion to its area with the first dimension of the
+    // sample, which is then stretched back over [0, 1) to pick a point of it
+    fn sample_surface(&self, origin: Point, u: (f64, f64)) -> Option<SurfaceSample> {
+        let total = self.area();
+        if total == 0f64 {
+            return None;
+        }
+        let target = u.0 * total;
+        let index = self.areas.partition_point(|&area| area <= target).min(self.areas.len() - 1);
+        let area = self.face_area(index);
+        let start = self.areas[index] - area;
+        let u = (((target - start) / area).clamp(0f64, 1f64), u.1);
+        let sample = triangle::sample_surface(&self.corners(index), origin, u)?;
+        Some(SurfaceSample {
+            pdf: sample.pdf * area / total,
+            ..sample
+        })
+    }
+
+    fn surface_pdf(&self, origin: Point, hit: &Intersection) -> f64 {
+        // Find the face hit again, by tracing from `origin` to around the point hit
+        let to_hit = hit.point - origin;
+        let distance = to_hit.len();
+        let range = (distance * (1f64 - 1e-6), distance * (1f64 + 1e-6));
+        let ray = Ray::new(origin, to_hit);
+        match self.bvh.hit_with(&ray, range, |index, range| self.intersects_face(index, &ray, range)) {
+            Some((_, index)) => {
+                triangle::surface_pdf(&self.corners(index), origin, hit) * self.face_area(index) / self.area()
+            }
+            None => 0f64,
+        }
+    }
+}
+
+impl Debug for Mesh {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "Mesh of {} triangles in {:?}", self.faces.len(), self.bbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::material::matte::Matte;
+    use crate::scene::sampler::{IndependentSampler, Sampler};
+
+    #[test]
+    fn test_mesh_closest_triangle() {
+        let material: Arc<dyn Material> = Arc::new(Matte::new(RgbColor::new(0.5, 0.5, 0.5)));
+        // A stack of triangles one unit apart along -z
+        let positions = (1..=10)
+            .flat_map(|i| {
+                let z = -(i as f64);
+                [Point::new(-1f64, -1f64, z), Point::new(1f64, -1f64, z), Point::new(0f64, 1f64, z)]
+            })
+            .collect();
+        let faces = (0..10).map(|i| [3 * i, 3 * i + 1, 3 * i + 2]).collect();
+        let mesh = Mesh::new(positions, faces, material);
+        assert_eq!(mesh.len(), 10);
+        assert_eq!(mesh.bounding_box().min.z, -10f64 - 1e-6);
+
+        let ray = Ray::new(Point::default(), Vector::new(0f64, 0f64, -1f64));
+        let hit = mesh.intersects(&ray, (0.001, f64::INFINITY)).unwrap();
+        assert!((hit.t - 1f64).abs() < 1e-12);
+        let hit = mesh.intersects(&ray, (4.5, f64::INFINITY)).unwrap();
+        assert!((hit.t - 5f64).abs() < 1e-12);
+        let beside = Ray::new(Point::new(3f64, 0f64, 0f64), Vector::new(0f64, 0f64, -1f64));
+        assert!(mesh.intersects(&beside, (0.001, f64::INFINITY)).is_none());
+    }
+
+    #[test]
+    fn test_mesh_shared_vertex_attributes() {
+        // A square of two faces sharing the diagonal from vertex 0 to vertex 2, with a colour and a normal per vertex
+        let material: Arc<dyn Material> = Arc::new(Matte::new(RgbColor::new(0.5, 0.5, 0.5)));
+        let positions = vec![
+            Point::new(0f64, 0f64, -1f64),
+            Point::new(1f64, 0f64, -1f64),
+            Point::new(1f64, 1f64, -1f64),
+            Point::new(0f64, 1f64, -1f64),
+        ];
+        let up = Vector::new(0f64, 0f64, 1f64);
+        let tilted = Vector::unit(Vector::new(1f64, 0f64, 1f64));
+        let (red, blue) = (RgbColor::new(1f64, 0f64, 0f64), RgbColor::new(0f64, 0f64, 1f64));
+        let mesh = Mesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], material)
+            .with_normals(vec![up, up, tilted, up])
+            .with_colors(vec![red, blue, blue, blue])
+            .with_uvs(vec![(0f64, 0f64), (1f64, 0f64), (1f64, 1f64), (0f64, 1f64)]);
+        // Both faces agree along the diagonal they share
+        let towards = |x: f64, y: f64| Ray::new(Point::new(x, y, 0f64), Vector::new(0f64, 0f64, -1f64));
+        for (x, y) in [(0.6f64, 0.5f64), (0.5f64, 0.5f64), (0.4f64, 0.5f64), (0.2f64, 0.7f64)] {
+            let hit = mesh.intersects(&towards(x, y), (0.001, f64::INFINITY)).unwrap();
+            assert!((hit.uv.0 - x).abs() < 1e-12 && (hit.uv.1 - y).abs() < 1e-12);
+            if x == y {
+                assert!((hit.color.unwrap() - RgbColor::new(1f64 - x, 0f64, x)).len() < 1e-12);
+                assert!(hit.normal.x > 0f64 && hit.normal.x < tilted.x);
+            }
+        }
+        let hit = mesh.intersects(&towards(0f64, 1f64), (0.001, f64::INFINITY)).unwrap();
+        assert!((hit.normal - up).len() < 1e-12);
+        assert_eq!(hit.color, Some(blue));
+    }
+
+    #[test]
+    fn test_mesh_sample_surface() {
+        // Two triangles of a quad with areas 1.5 and 0.5, which are picked three times out of four and once
+        let material: Arc<dyn Material> = Arc::new(Matte::new(RgbColor::new(0.5, 0.5, 0.5)));
+        let (a, b, c, d) = (
+            Point::new(0f64, 0f64, -2f64),
+            Point::new(3f64, 0f64, -2f64),
+            Point::new(0f64, 1f64, -2f64),
+            Point::new(1f64, 1f64, -2f64),
+        );
+        let mesh = Mesh::new(vec![a, b, c, d], vec![[0, 1, 2], [1, 3, 2]], material);
+        assert!((mesh.area() - 2f64).abs() < 1e-12);
+        let origin = Point::new(1f64, 0.5f64, 0f64);
+        let mut sampler = IndependentSampler::new(7);
+        let mut first = 0;
+        let samples = 10_000;
+        for _ in 0..samples {
+            let sample = mesh.sample_surface(origin, sampler.get_2d()).unwrap();
+            let ray = Ray::new(origin, sample.point - origin);
+            let hit = mesh.intersects(&ray, (0.001, f64::INFINITY)).unwrap();
+            assert!((hit.point - sample.point).len() < 1e-9);
+            assert!((mesh.surface_pdf(origin, &hit) - sample.pdf).abs() < 1e-9 * sample.pdf);
+            assert!(mesh.occludes(&ray, (0.001, f64::INFINITY)));
+            if sample.point.x + 3f64 * sample.point.y < 3f64 {
+                first += 1;
+            }
+        }
+        assert!((first as f64 / samples as f64 - 0.75f64).abs() < 0.02);
+    }
+}
@@ -1,3 +1,8 @@
+pub mod aabb;
 pub mod vec3;
 pub mod vec4;
-pub mod sphere;
\ No newline at end of file
+pub mod sphere;
+pub mod triangle;
+pub mod mesh;
+pub mod frame;
+pub mod sampling;
\ No newline at end of file
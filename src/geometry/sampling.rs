@@ -0,0 +1,266 @@
+use crate::geometry::vec3::Vector;
+use std::f64::consts::{FRAC_1_PI, FRAC_PI_4, PI};
+
+// Sampling
+// Warps of uniform samples of the unit square onto the shapes a path tracer draws directions and points from, each
+// with the density it produces. The hemispheres are around the z axis, use a `Frame` to orient them around a normal.
+// Densities are per unit solid angle for directions and per unit area or volume for points.
+
+/// Uniformly distributed direction
+pub fn uniform_sphere((u, v): (f64, f64)) -> Vector {
+    // Archimedes: the height on the sphere of a uniform point is itself uniform
+    let z = 1f64 - 2f64 * u;
+    let r = (1f64 - z * z).max(0f64).sqrt();
+    let phi = 2f64 * PI * v;
+    Vector::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_sphere_pdf() -> f64 {
+    1f64 / (4f64 * PI)
+}
+
+/// Uniformly distributed direction with a positive z
+pub fn uniform_hemisphere((u, v): (f64, f64)) -> Vector {
+    let z = u;
+    let r = (1f64 - z * z).max(0f64).sqrt();
+    let phi = 2f64 * PI * v;
+    Vector::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_hemisphere_pdf() -> f64 {
+    1f64 / (2f64 * PI)
+}
+
+/// Direction with a positive z, with a density proportional to the cosine of its angle to the z axis
+///
+/// Malley's method, a uniform point of the unit disk lifted up onto the hemisphere.
+pub fn cosine_hemisphere(u: (f64, f64)) -> Vector {
+    let (x, y) = unit_disk(u);
+    Vector::new(x, y, (1f64 - x * x - y * y).max(0f64).sqrt())
+}
+
+/// Density of `cosine_hemisphere` for a direction making an angle θ with the z axis
+pub fn cosine_hemisphere_pdf(cos_theta: f64) -> f64 {
+    cos_theta.max(0f64) * FRAC_1_PI
+}
+
+/// Direction with a positive z, with a density proportional to the cosine of its angle to the z axis raised to
+/// `exponent`, the lobe of a Phong highlight. The higher the exponent the tighter the lobe, 0 is uniform.
+pub fn phong_lobe((u, v): (f64, f64), exponent: f64) -> Vector {
+    // The cosine raised to exponent + 1 is uniform
+    let z = u.powf(1f64 / (exponent + 1f64));
+    let r = (1f64 - z * z).max(0f64).sqrt();
+    let phi = 2f64 * PI * v;
+    Vector::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Density of `phong_lobe` for a direction making an angle θ with the z axis
+pub fn phong_lobe_pdf(cos_theta: f64, exponent: f64) -> f64 {
+    (exponent + 1f64) / (2f64 * PI) * cos_theta.max(0f64).powf(exponent)
+}
+
+/// Uniformly distributed direction within `cos_theta_max` of the z axis, the directions towards a sphere seen from
+/// outside of it
+pub fn uniform_cone((u, v): (f64, f64), cos_theta_max: f64) -> Vector {
+    let z = 1f64 - u * (1f64 - cos_theta_max);
+    let r = (1f64 - z * z).max(0f64).sqrt();
+    let phi = 2f64 * PI * v;
+    Vector::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_cone_pdf(cos_theta_max: f64) -> f64 {
+    1f64 / (2f64 * PI * (1f64 - cos_theta_max))
+}
+
+/// Uniformly distributed point of the unit disk
+///
+/// Shirley and Chiu's concentric mapping, which maps squares around the centre of the unit square to circles and
+/// keeps neighbouring samples close together.
+pub fn unit_disk((u, v): (f64, f64)) -> (f64, f64) {
+    let (a, b) = (2f64 * u - 1f64, 2f64 * v - 1f64);
+    if a == 0f64 && b == 0f64 {
+        return (0f64, 0f64);
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, 2f64 * FRAC_PI_4 - FRAC_PI_4 * (a / b))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
+pub fn unit_disk_pdf() -> f64 {
+    FRAC_1_PI
+}
+
+/// Uniformly distributed point of the unit ball, from a 2D sample for the direction and a 1D sample for the radius
+pub fn unit_ball(u: (f64, f64), w: f64) -> Vector {
+    uniform_sphere(u) * w.cbrt()
+}
+
+pub fn unit_ball_pdf() -> f64 {
+    3f64 / (4f64 * PI)
+}
+
+/// Barycentric weights (w0, w1, w2) of a uniformly distributed point of a triangle, with a density of one over its
+/// area
+pub fn uniform_triangle((u, v): (f64, f64)) -> (f64, f64, f64) {
+    let su = u.sqrt();
+    let (w0, w1) = (1f64 - su, v * su);
+    (w0, w1, (1f64 - w0 - w1).max(0f64))
+}
+
+/// Density per unit solid angle, seen from `origin`, of a point of a surface picked with density `pdf` per unit area
+///
+/// A small patch of the surface covers a solid angle shrinking with the square of its distance and with the cosine of
+/// its tilt away from `origin`. Infinite for points of surfaces seen edge on.
+pub fn solid_angle_pdf(pdf: f64, origin: Vector, point: Vector, normal: Vector) -> f64 {
+    let to_point = point - origin;
+    let distance_sq = to_point.len_sq();
+    let cosine = Vector::dot(normal, to_point).abs() / distance_sq.sqrt();
+    pdf * distance_sq / cosine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const SAMPLES: usize = 64_000;
+    // 99.9th percentile of the chi-square distribution with 63 degrees of freedom
+    const CHI_SQUARE_CRITICAL: f64 = 103.4;
+
+    // Chi-square statistic of samples put in 64 equally likely bins by `bin`
+    fn chi_square(mut sample: impl FnMut(&mut StdRng) -> usize) -> f64 {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut bins = [0usize; 64];
+        for _ in 0..SAMPLES {
+            bins[sample(&mut rng)] += 1;
+        }
+        let expected = SAMPLES as f64 / 64f64;
+        bins.iter().map(|&n| (n as f64 - expected).powi(2) / expected).sum()
+    }
+
+    fn square(rng: &mut StdRng) -> (f64, f64) {
+        (rng.gen_range(0f64..1f64), rng.gen_range(0f64..1f64))
+    }
+
+    // Bin of a value in [0, 1) and an angle, 8 x 8
+    fn bin(a: f64, phi: f64) -> usize {
+        let phi = (phi + 2f64 * PI) % (2f64 * PI);
+        ((a * 8f64) as usize).min(7) * 8 + ((phi / (2f64 * PI) * 8f64) as usize).min(7)
+    }
+
+    #[test]
+    fn test_uniform_sphere() {
+        // Equal ranges of heights cut the sphere into equal areas
+        let statistic = chi_square(|rng| {
+            let d = uniform_sphere(square(rng));
+            assert!((d.len() - 1f64).abs() < 1e-12);
+            bin((d.z + 1f64) / 2f64, d.y.atan2(d.x))
+        });
+        assert!(statistic < CHI_SQUARE_CRITICAL, "{}", statistic);
+    }
+
+    #[test]
+    fn test_uniform_hemisphere() {
+        let statistic = chi_square(|rng| {
+            let d = uniform_hemisphere(square(rng));
+            assert!(d.z >= 0f64 && (d.len() - 1f64).abs() < 1e-12);
+            bin(d.z, d.y.atan2(d.x))
+        });
+        assert!(statistic < CHI_SQUARE_CRITICAL, "{}", statistic);
+    }
+
+    #[test]
+    fn test_cosine_hemisphere() {
+        // With a density of cosθ/π, cos²θ is uniform
+        let statistic = chi_square(|rng| {
+            let d = cosine_hemisphere(square(rng));
+            assert!(d.z >= 0f64 && (d.len() - 1f64).abs() < 1e-12);
+            bin(d.z * d.z, d.y.atan2(d.x))
+        });
+        assert!(statistic < CHI_SQUARE_CRITICAL, "{}", statistic);
+    }
+
+    #[test]
+    fn test_phong_lobe() {
+        let statistic = chi_square(|rng| {
+            let d = phong_lobe(square(rng), 20f64);
+            assert!(d.z >= 0f64 && (d.len() - 1f64).abs() < 1e-12);
+            bin(d.z.powi(21), d.y.atan2(d.x))
+        });
+        assert!(statistic < CHI_SQUARE_CRITICAL, "{}", statistic);
+    }
+
+    #[test]
+    fn test_uniform_cone() {
+        // Within the cone the height is uniform, as on the whole sphere
+        let cos_theta_max = 0.8f64;
+        let statistic = chi_square(|rng| {
+            let d = uniform_cone(square(rng), cos_theta_max);
+            assert!(d.z >= cos_theta_max - 1e-12 && (d.len() - 1f64).abs() < 1e-12);
+            bin((d.z - cos_theta_max) / (1f64 - cos_theta_max), d.y.atan2(d.x))
+        });
+        assert!(statistic < CHI_SQUARE_CRITICAL, "{}", statistic);
+    }
+
+    #[test]
+    fn test_uniform_triangle() {
+        // (1 - w0)² is uniform, and so is w1 / (1 - w0) whatever w0
+        let statistic = chi_square(|rng| {
+            let (w0, w1, w2) = uniform_triangle(square(rng));
+            assert!(w0 >= 0f64 && w1 >= 0f64 && w2 >= 0f64 && (w0 + w1 + w2 - 1f64).abs() < 1e-12);
+            bin((1f64 - w0).powi(2), w1 / (1f64 - w0) * 2f64 * PI)
+        });
+        assert!(statistic < CHI_SQUARE_CRITICAL, "{}", statistic);
+    }
+
+    #[test]
+    fn test_unit_disk() {
+        // Equal ranges of squared radii cut the disk into equal areas
+        let statistic = chi_square(|rng| {
+            let (x, y) = unit_disk(square(rng));
+            let r_sq = x * x + y * y;
+            assert!(r_sq <= 1f64 + 1e-12);
+            bin(r_sq, y.atan2(x))
+        });
+        assert!(statistic < CHI_SQUARE_CRITICAL, "{}", statistic);
+    }
+
+    #[test]
+    fn test_unit_ball() {
+        // The cubed radius and the height of the direction are both uniform, and independent
+        let statistic = chi_square(|rng| {
+            let p = unit_ball(square(rng), rng.gen_range(0f64..1f64));
+            let r = p.len();
+            assert!(r <= 1f64 + 1e-12);
+            bin(r.powi(3), (p.z / r + 1f64) / 2f64 * 2f64 * PI)
+        });
+        assert!(statistic < CHI_SQUARE_CRITICAL, "{}", statistic);
+    }
+
+    #[test]
+    fn test_pdfs_integrate_to_one() {
+        // Monte Carlo integrals over the hemisphere, with uniform directions
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut integrate = |f: &dyn Fn(Vector) -> f64| {
+            (0..SAMPLES)
+                .map(|_| f(uniform_hemisphere(square(&mut rng))) / uniform_hemisphere_pdf())
+                .sum::<f64>()
+                / SAMPLES as f64
+        };
+        let cosine = integrate(&|d| cosine_hemisphere_pdf(d.z));
+        assert!((cosine - 1f64).abs() < 0.01, "{}", cosine);
+        let phong = integrate(&|d| phong_lobe_pdf(d.z, 3f64));
+        assert!((phong - 1f64).abs() < 0.01, "{}", phong);
+        let cone = integrate(&|d| if d.z >= 0.5f64 { uniform_cone_pdf(0.5f64) } else { 0f64 });
+        assert!((cone - 1f64).abs() < 0.01, "{}", cone);
+        let uniform = integrate(&|_| uniform_hemisphere_pdf());
+        assert!((uniform - 1f64).abs() < 1e-9, "{}", uniform);
+        assert!((uniform_sphere_pdf() * 4f64 * PI - 1f64).abs() < 1e-12);
+        assert!((unit_disk_pdf() * PI - 1f64).abs() < 1e-12);
+        assert!((unit_ball_pdf() * 4f64 / 3f64 * PI - 1f64).abs() < 1e-12);
+    }
+}
@@ -1,28 +1,35 @@
 use crate::color::rgb::RgbColor;
+use crate::geometry::aabb::Aabb;
+use crate::geometry::frame::Frame;
+use crate::geometry::sampling;
 use crate::geometry::vec3::Vector as Point;
 use crate::geometry::vec3::Vector;
 use crate::scene::intersect::Intersect;
 use crate::scene::intersect::Intersection;
+use crate::scene::intersect::SurfaceSample;
 use crate::scene::material::Material;
 use crate::scene::ray::Ray;
+use std::f64::consts::PI;
 use std::fmt::{Debug, Formatter, Result};
 
 pub struct Sphere {
     center: Point,
     radius: f64,
-    material: Box<dyn Material<Albedo = RgbColor>>,
+    material: Box<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new(center: Point, radius: f64, material: Box<dyn Material<Albedo = RgbColor>>) -> Self {
+    pub fn new(center: Point, radius: f64, material: Box<dyn Material>) -> Self {
         Sphere {
             center,
             radius,
             material,
         }
     }
+}
 
-    pub fn default() -> Self {
+impl Default for Sphere {
+    fn default() -> Self {
         use crate::scene::material::matte;
 
         Sphere {
@@ -38,18 +45,23 @@ impl Sphere {
 }
 
 impl Intersect for Sphere {
-    fn material(&self) -> &dyn Material<Albedo = RgbColor> {
+    fn material(&self) -> &dyn Material {
         // time to do some sketchy shit doo-dah doo-dah
         &*self.material
     }
 
+    fn bounding_box(&self) -> Aabb {
+        let r = Vector::new(self.radius.abs(), self.radius.abs(), self.radius.abs());
+        Aabb::new(self.center - r, self.center + r)
+    }
+
     // Intersection mathemetics
     // A ray in 3d space; r(t) = A + tB, where A is the origin and B is a unit vector
     // A sphere is; x^2 + y^2 + z^2 = r^2, where r is the radius of the sphere and origin is at (0,0,0)
     // the radius of the sphere, r = P - C, where P is any point on the circle an
     // if the ray intersects the sphere, at least one point will satisy both the equations
     // t^2 + 2t(A-C) + (A-C)(A-C) - r^2 = 0
-    fn intersects(self: &Self, r: &Ray, range: (f64, f64)) -> Option<Intersection> {
+    fn intersects(&self, r: &Ray, range: (f64, f64)) -> Option<Intersection> {
         let oc = r.origin - self.center;
         // ignorimg a because we create rays with unit direction
         // let a = r.direction.len_sq();
@@ -79,7 +91,46 @@ impl Intersect for Sphere {
             normal = normal * -1f64;
         }
 
-        Some(Intersection::new(r.at(root), normal, root))
+        Some(Intersection::new(r.at(root), normal, root, front_face))
+    }
+
+    // From outside, only the cap of the sphere facing `origin` can be seen, and it fills a cone of directions around
+    // the centre: sampling the cone uniformly never wastes a sample on the far side. From inside, every point can be
+    // seen and is picked uniformly by area.
+    fn sample_surface(&self, origin: Point, u: (f64, f64)) -> Option<SurfaceSample> {
+        let radius = self.radius.abs();
+        let to_center = self.center - origin;
+        let distance_sq = to_center.len_sq();
+        if distance_sq <= radius * radius {
+            let normal = sampling::uniform_sphere(u);
+            let point = self.center + normal * radius;
+            let pdf = sampling::solid_angle_pdf(1f64 / (4f64 * PI * radius * radius), origin, point, normal);
+            return pdf.is_finite().then_some(SurfaceSample { point, normal, pdf });
+        }
+
+        // Cone of half angle θmax, sinθmax = r / d
+        let distance = distance_sq.sqrt();
+        let cos_theta_max = (1f64 - radius * radius / distance_sq).max(0f64).sqrt();
+        let local = sampling::uniform_cone(u, cos_theta_max);
+        let direction = Frame::from_normal(to_center / distance).to_world(local);
+        // Nearest root of |origin + t * direction - center| = r
+        let sin_sq = (1f64 - local.z * local.z).max(0f64);
+        let t = distance * local.z - (radius * radius - distance_sq * sin_sq).max(0f64).sqrt();
+        let point = origin + direction * t;
+        Some(SurfaceSample {
+            point,
+            normal: (point - self.center) / radius,
+            pdf: sampling::uniform_cone_pdf(cos_theta_max),
+        })
+    }
+
+    fn surface_pdf(&self, origin: Point, hit: &Intersection) -> f64 {
+        let radius = self.radius.abs();
+        let distance_sq = (self.center - origin).len_sq();
+        if distance_sq <= radius * radius {
+            return sampling::solid_angle_pdf(1f64 / (4f64 * PI * radius * radius), origin, hit.point, hit.normal);
+        }
+        sampling::uniform_cone_pdf((1f64 - radius * radius / distance_sq).max(0f64).sqrt())
     }
 }
 
@@ -93,24 +144,74 @@ impl Debug for Sphere {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::scene::material::matte;
+    use crate::scene::material::matte::Matte;
+    use crate::scene::sampler::{IndependentSampler, Sampler};
 
     #[test]
     fn test_default_sphere() {
-        let material = Box::new(matte::Matte {
-            albedo: RgbColor::default(),
-        });
+        let sphere = Sphere::default();
         assert_eq!(
-            Sphere::default(),
-            Sphere {
-                center: Point {
-                    x: 0f64,
-                    y: 0f64,
-                    z: 0f64
-                },
-                radius: 1f64,
-                material
+            sphere.center,
+            Point {
+                x: 0f64,
+                y: 0f64,
+                z: 0f64
             }
         );
+        assert_eq!(sphere.radius, 1f64);
+        assert_eq!(
+            sphere.material().albedo(),
+            RgbColor::new(0.8f64, 0.8f64, 0.8f64)
+        );
+    }
+
+    #[test]
+    fn test_sphere_front_face() {
+        let sphere = Sphere::default();
+        // From outside the ray hits the front face and the normal points back at the ray
+        let outside = Ray::new(Point::new(0f64, 0f64, 5f64), Vector::new(0f64, 0f64, -1f64));
+        let hit = sphere.intersects(&outside, (0.001, f64::INFINITY)).unwrap();
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, Vector::new(0f64, 0f64, 1f64));
+        // From inside the ray hits the back face and the normal is flipped to face the ray
+        let inside = Ray::new(Point::default(), Vector::new(0f64, 0f64, -1f64));
+        let hit = sphere.intersects(&inside, (0.001, f64::INFINITY)).unwrap();
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Vector::new(0f64, 0f64, 1f64));
+    }
+
+    #[test]
+    fn test_sample_surface_sees_the_near_side() {
+        // Points picked from outside are on the cap facing the origin, and a ray towards them hits them first
+        let sphere = Sphere::new(Point::new(0f64, 0f64, -3f64), 1f64, Box::new(Matte::new(RgbColor::new(0.5f64, 0.5f64, 0.5f64))));
+        let origin = Point::new(0.5f64, 0.2f64, 0f64);
+        let mut sampler = IndependentSampler::new(4);
+        for _ in 0..100 {
+            let sample = sphere.sample_surface(origin, sampler.get_2d()).unwrap();
+            assert!(((sample.point - sphere.center).len() - 1f64).abs() < 1e-9);
+            assert!(Vector::dot(sample.normal, origin - sample.point) >= -1e-9);
+            let ray = Ray::new(origin, sample.point - origin);
+            let hit = sphere.intersects(&ray, (0.001, f64::INFINITY)).unwrap();
+            assert!((hit.point - sample.point).len() < 1e-6);
+            assert!((sphere.surface_pdf(origin, &hit) - sample.pdf).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_surface_pdf_integrates_to_one() {
+        // Over all directions from a point, outside and then inside the sphere
+        let sphere = Sphere::new(Point::new(0f64, 0f64, -2f64), 1f64, Box::new(Matte::new(RgbColor::new(0.5f64, 0.5f64, 0.5f64))));
+        let mut sampler = IndependentSampler::new(5);
+        for origin in [Point::default(), Point::new(0f64, 0.3f64, -2.2f64)] {
+            let samples = 100_000;
+            let mut integral = 0f64;
+            for _ in 0..samples {
+                let direction = sampling::uniform_sphere(sampler.get_2d());
+                if let Some(hit) = sphere.intersects(&Ray::new(origin, direction), (0f64, f64::INFINITY)) {
+                    integral += sphere.surface_pdf(origin, &hit) / sampling::uniform_sphere_pdf();
+                }
+            }
+            assert!((integral / samples as f64 - 1f64).abs() < 0.02, "{}", integral / samples as f64);
+        }
     }
 }
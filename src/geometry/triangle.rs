@@ -0,0 +1,383 @@
+use crate::color::rgb::RgbColor;
+use crate::geometry::aabb::Aabb;
+use crate::geometry::sampling;
+use crate::geometry::vec3::Vector as Point;
+use crate::geometry::vec3::Vector;
+use crate::scene::intersect::Intersect;
+use crate::scene::intersect::Intersection;
+use crate::scene::intersect::SurfaceSample;
+use crate::scene::material::Material;
+use crate::scene::ray::Ray;
+use std::fmt::{Debug, Formatter, Result};
+use std::sync::Arc;
+
+// Determinants smaller than this mean the ray runs parallel to the plane of the triangle
+const PARALLEL_EPSILON: f64 = 1e-12;
+
+// Padding added to the bounding box, a triangle lying in an axis plane would otherwise get a box with no thickness
+const BOX_PADDING: f64 = 1e-6;
+
+/// Flat triangle between three vertices, the building block of meshes
+///
+/// The normal is the geometric one unless per-vertex normals are given, in which case they are interpolated across
+/// the face for smooth shading. The material is shared so that all the triangles of a mesh can use the same one.
+///
+/// # Examples
+/// ```
+/// # use rusttracing::geometry::triangle::Triangle;
+/// # use rusttracing::geometry::vec3::Vector;
+/// # use rusttracing::scene::intersect::Intersect;
+/// # use rusttracing::scene::material::matte::Matte;
+/// # use rusttracing::scene::ray::Ray;
+/// # use std::sync::Arc;
+/// let triangle = Triangle::new(
+///     [Vector::new(-1f64, -1f64, -1f64), Vector::new(1f64, -1f64, -1f64), Vector::new(0f64, 1f64, -1f64)],
+///     Arc::new(Matte::new(Vector::new(0.5f64, 0.5f64, 0.5f64))),
+/// );
+/// let ray = Ray::new(Vector::default(), Vector::new(0f64, 0f64, -1f64));
+/// let hit = triangle.intersects(&ray, (0.001, f64::INFINITY)).unwrap();
+/// assert_eq!(hit.t, 1f64);
+/// ```
+pub struct Triangle {
+    vertices: [Point; 3],
+    normals: Option<[Vector; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    colors: Option<[RgbColor; 3]>,
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Point; 3], material: Arc<dyn Material>) -> Self {
+        Triangle {
+            vertices,
+            normals: None,
+            uvs: None,
+            colors: None,
+            material,
+        }
+    }
+
+    /// Shades the triangle with normals interpolated between the given vertex normals
+    pub fn with_normals(self, normals: [Vector; 3]) -> Self {
+        Triangle {
+            normals: Some(normals),
+            ..self
+        }
+    }
+
+    /// Texture coordinates of the vertices, without them the vertices sit at (0, 0), (1, 0) and (0, 1)
+    pub fn with_uvs(self, uvs: [(f64, f64); 3]) -> Self {
+        Triangle {
+            uvs: Some(uvs),
+            ..self
+        }
+    }
+
+    /// Colours of the vertices, interpolated across the face and reported in the intersections
+    pub fn with_colors(self, colors: [RgbColor; 3]) -> Self {
+        Triangle {
+            colors: Some(colors),
+            ..self
+        }
+    }
+
+    pub fn vertices(&self) -> &[Point; 3] {
+        &self.vertices
+    }
+
+    pub fn area(&self) -> f64 {
+        area(&self.vertices)
+    }
+}
+
+impl Intersect for Triangle {
+    fn material(&self) -> &dyn Material {
+        &*self.material
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        bounding_box(&self.vertices)
+    }
+
+    fn intersects(&self, r: &Ray, range: (f64, f64)) -> Option<Intersection> {
+        let hit = intersect(&self.vertices, r, range)?;
+        Some(shade(&self.vertices, r, hit, self.normals, self.uvs, self.colors))
+    }
+
+    fn occludes(&self, r: &Ray, range: (f64, f64)) -> bool {
+        intersect(&self.vertices, r, range).is_some()
+    }
+
+    fn sample_surface(&self, origin: Point, u: (f64, f64)) -> Option<SurfaceSample> {
+        sample_surface(&self.vertices, origin, u)
+    }
+
+    fn surface_pdf(&self, origin: Point, hit: &Intersection) -> f64 {
+        surface_pdf(&self.vertices, origin, hit)
+    }
+}
+
+// The geometry of a triangle, shared by `Triangle` and the faces of a `Mesh`, which keep their vertices in different
+// places
+
+pub(crate) fn area([v0, v1, v2]: &[Point; 3]) -> f64 {
+    Vector::cross(*v1 - *v0, *v2 - *v0).len() / 2f64
+}
+
+// Unit normal of the plane of the triangle, facing the side the vertices wind counter-clockwise on
+fn geometric_normal([v0, v1, v2]: &[Point; 3]) -> Vector {
+    Vector::unit(Vector::cross(*v1 - *v0, *v2 - *v0))
+}
+
+pub(crate) fn bounding_box(&[a, b, c]: &[Point; 3]) -> Aabb {
+    let aabb = Aabb::grow(Aabb::grow(Aabb::new(a, a), b), c);
+    let padding = Vector::new(BOX_PADDING, BOX_PADDING, BOX_PADDING);
+    Aabb::new(aabb.min - padding, aabb.max + padding)
+}
+
+// Möller–Trumbore
+// Any point on the triangle is P = (1 - u - v)V0 + uV1 + vV2 for u, v >= 0 and u + v <= 1
+// Setting it equal to the ray O + tD and rearranging gives the linear system
+// [-D, E1, E2] (t, u, v) = O - V0, where E1 = V1 - V0 and E2 = V2 - V0
+// which is solved with Cramer's rule, the determinants written as scalar triple products. Returns (t, u, v).
+pub(crate) fn intersect(&[v0, v1, v2]: &[Point; 3], r: &Ray, range: (f64, f64)) -> Option<(f64, f64, f64)> {
+    let e1 = v1 - v0;
+    let e2 = v2 - v0;
+
+    // 1. Determinant of the system, zero when the ray is parallel to the triangle
+    let p = Vector::cross(r.direction, e2);
+    let det = Vector::dot(e1, p);
+    if det.abs() < PARALLEL_EPSILON {
+        return None;
+    }
+    let inv_det = 1f64 / det;
+
+    // 2. Barycentric coordinates, the hit has to lie inside all three edges
+    let s = r.origin - v0;
+    let u = Vector::dot(s, p) * inv_det;
+    if !(0f64..=1f64).contains(&u) {
+        return None;
+    }
+    let q = Vector::cross(s, e1);
+    let v = Vector::dot(r.direction, q) * inv_det;
+    if v < 0f64 || u + v > 1f64 {
+        return None;
+    }
+
+    // 3. Distance along the ray
+    let t = Vector::dot(e2, q) * inv_det;
+    if t < range.0 || t > range.1 {
+        return None;
+    }
+    Some((t, u, v))
+}
+
+// Intersection for a hit found by `intersect`, with the attributes of the vertices interpolated at it
+pub(crate) fn shade(
+    vertices: &[Point; 3],
+    r: &Ray,
+    (t, u, v): (f64, f64, f64),
+    normals: Option<[Vector; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    colors: Option<[RgbColor; 3]>,
+) -> Intersection {
+    let w = 1f64 - u - v;
+
+    // The winding of the vertices decides the front of the triangle, counter-clockwise when looking at it
+    let geometric_normal = geometric_normal(vertices);
+    let front_face = Vector::dot(geometric_normal, r.direction) < 0f64;
+    let facing = if front_face { geometric_normal } else { geometric_normal * -1f64 };
+    // Interpolated normals are brought over to the side of the ray. Near the silhouette of a smooth mesh they can still
+    // lean away from it, and the materials only scatter light to the side of the normal, so the geometric normal is
+    // used there instead.
+    let normal = match normals {
+        Some([n0, n1, n2]) => {
+            let mut normal = Vector::unit(n0 * w + n1 * u + n2 * v);
+            if Vector::dot(normal, facing) < 0f64 {
+                normal = normal * -1f64;
+            }
+            if Vector::dot(normal, r.direction) < 0f64 {
+                normal
+            } else {
+                facing
+            }
+        }
+        None => facing,
+    };
+
+    let uv = match uvs {
+        Some([uv0, uv1, uv2]) => (
+            w * uv0.0 + u * uv1.0 + v * uv2.0,
+            w * uv0.1 + u * uv1.1 + v * uv2.1,
+        ),
+        None => (u, v),
+    };
+
+    let intersection = Intersection::new(r.at(t), normal, t, front_face)
+        .with_uv(uv)
+        .with_barycentric((w, u, v));
+    match colors {
+        Some([c0, c1, c2]) => intersection.with_color(c0 * w + c1 * u + c2 * v),
+        None => intersection,
+    }
+}
+
+// Uniform by area, with the geometric normal: interpolated normals only change the shading
+pub(crate) fn sample_surface(vertices: &[Point; 3], origin: Point, u: (f64, f64)) -> Option<SurfaceSample> {
+    let area = area(vertices);
+    if area == 0f64 {
+        return None;
+    }
+    let [v0, v1, v2] = *vertices;
+    let (w0, w1, w2) = sampling::uniform_triangle(u);
+    let point = v0 * w0 + v1 * w1 + v2 * w2;
+    let normal = geometric_normal(vertices);
+    let pdf = sampling::solid_angle_pdf(1f64 / area, origin, point, normal);
+    pdf.is_finite().then_some(SurfaceSample { point, normal, pdf })
+}
+
+pub(crate) fn surface_pdf(vertices: &[Point; 3], origin: Point, hit: &Intersection) -> f64 {
+    sampling::solid_angle_pdf(1f64 / area(vertices), origin, hit.point, geometric_normal(vertices))
+}
+
+impl Debug for Triangle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "Triangle {:?}", self.vertices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::material::matte::Matte;
+    use crate::scene::sampler::{IndependentSampler, Sampler};
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            [
+                Point::new(0f64, 0f64, -2f64),
+                Point::new(1f64, 0f64, -2f64),
+                Point::new(0f64, 1f64, -2f64),
+            ],
+            Arc::new(Matte::new(RgbColor::new(0.5f64, 0.5f64, 0.5f64))),
+        )
+    }
+
+    fn towards(x: f64, y: f64) -> Ray {
+        Ray::new(Point::new(x, y, 0f64), Vector::new(0f64, 0f64, -1f64))
+    }
+
+    #[test]
+    fn test_triangle_hit_and_miss() {
+        let triangle = triangle();
+        let hit = triangle.intersects(&towards(0.25, 0.25), (0.001, f64::INFINITY)).unwrap();
+        assert!((hit.t - 2f64).abs() < 1e-12);
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, Vector::new(0f64, 0f64, 1f64));
+        // Outside the hypotenuse, behind the ray and out of range
+        assert!(triangle.intersects(&towards(0.6, 0.6), (0.001, f64::INFINITY)).is_none());
+        assert!(triangle.intersects(&towards(-0.1, 0.5), (0.001, f64::INFINITY)).is_none());
+        assert!(triangle.intersects(&towards(0.25, 0.25), (0.001, 1f64)).is_none());
+        // Parallel to the plane of the triangle
+        let parallel = Ray::new(Point::new(-1f64, 0.25, -2f64), Vector::new(1f64, 0f64, 0f64));
+        assert!(triangle.intersects(&parallel, (0.001, f64::INFINITY)).is_none());
+    }
+
+    #[test]
+    fn test_triangle_back_face() {
+        let ray = Ray::new(Point::new(0.25, 0.25, -4f64), Vector::new(0f64, 0f64, 1f64));
+        let hit = triangle().intersects(&ray, (0.001, f64::INFINITY)).unwrap();
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Vector::new(0f64, 0f64, -1f64));
+    }
+
+    #[test]
+    fn test_triangle_barycentric() {
+        let triangle = triangle().with_uvs([(0f64, 0f64), (2f64, 0f64), (0f64, 4f64)]);
+        let hit = triangle.intersects(&towards(0.5, 0.25), (0.001, f64::INFINITY)).unwrap();
+        let (w, u, v) = hit.barycentric.unwrap();
+        assert!((w - 0.25).abs() < 1e-12 && (u - 0.5).abs() < 1e-12 && (v - 0.25).abs() < 1e-12);
+        // The weights reproduce the hit point
+        let [v0, v1, v2] = *triangle.vertices();
+        assert!((v0 * w + v1 * u + v2 * v - hit.point).len() < 1e-12);
+        assert!((hit.uv.0 - 1f64).abs() < 1e-12 && (hit.uv.1 - 1f64).abs() < 1e-12);
+        assert!(hit.color.is_none());
+        // Vertex colours are weighted the same way
+        let red = RgbColor::new(1f64, 0f64, 0f64);
+        let green = RgbColor::new(0f64, 1f64, 0f64);
+        let blue = RgbColor::new(0f64, 0f64, 1f64);
+        let hit = triangle
+            .with_colors([red, green, blue])
+            .intersects(&towards(0.5, 0.25), (0.001, f64::INFINITY))
+            .unwrap();
+        assert!((hit.color.unwrap() - RgbColor::new(0.25, 0.5, 0.25)).len() < 1e-12);
+    }
+
+    #[test]
+    fn test_triangle_interpolated_normals() {
+        let up = Vector::new(0f64, 0f64, 1f64);
+        let tilted = Vector::unit(Vector::new(1f64, 0f64, 1f64));
+        let triangle = triangle().with_normals([up, tilted, up]);
+        // At a vertex the normal is the vertex normal
+        let hit = triangle.intersects(&towards(1f64, 0f64), (0.001, f64::INFINITY)).unwrap();
+        assert!((hit.normal - tilted).len() < 1e-9);
+        // In between it is normalised
+        let hit = triangle.intersects(&towards(0.5, 0f64), (0.001, f64::INFINITY)).unwrap();
+        assert!((hit.normal.len() - 1f64).abs() < 1e-12);
+        assert!(hit.normal.x > 0f64 && hit.normal.x < tilted.x);
+        // Seen from behind the normals turn around with the face
+        let below = Ray::new(Point::new(0.5, 0f64, -4f64), Vector::new(0f64, 0f64, 1f64));
+        let hit = triangle.intersects(&below, (0.001, f64::INFINITY)).unwrap();
+        assert!(hit.normal.z < 0f64 && hit.normal.x < 0f64);
+        // A grazing ray the interpolated normal leans away from gets the geometric normal
+        let leaning = Vector::unit(Vector::new(1f64, 0f64, 0.2));
+        let triangle = triangle.with_normals([leaning; 3]);
+        let grazing = Ray::new(Point::new(0f64, 0.25, -1.7), Vector::new(1f64, 0f64, -0.6));
+        let hit = triangle.intersects(&grazing, (0.001, f64::INFINITY)).unwrap();
+        assert_eq!(hit.normal, Vector::new(0f64, 0f64, 1f64));
+    }
+
+    #[test]
+    fn test_triangle_shared_diagonal_interior_is_hit() {
+        // Two triangles forming a square, rays through points along the inside of the shared diagonal hit at least one
+        // of them. Not a proof of watertightness, Möller–Trumbore can still miss exactly on an edge.
+        let material: Arc<dyn Material> = Arc::new(Matte::new(RgbColor::new(0.5, 0.5, 0.5)));
+        let a = Point::new(0f64, 0f64, -2f64);
+        let b = Point::new(1f64, 0f64, -2f64);
+        let c = Point::new(1f64, 1f64, -2f64);
+        let d = Point::new(0f64, 1f64, -2f64);
+        let lower = Triangle::new([a, b, c], material.clone());
+        let upper = Triangle::new([a, c, d], material);
+        for i in 1..100 {
+            let s = i as f64 / 100f64;
+            let ray = towards(s, s);
+            let range = (0.001, f64::INFINITY);
+            assert!(lower.intersects(&ray, range).is_some() || upper.intersects(&ray, range).is_some());
+        }
+    }
+
+    #[test]
+    fn test_sample_surface_matches_surface_pdf() {
+        // Picked uniformly by area, so the solid angle density only depends on the distance and tilt of the point
+        let triangle = triangle();
+        assert!((triangle.area() - 0.5f64).abs() < 1e-12);
+        let origin = Point::new(0.2f64, 0.1f64, 0f64);
+        let mut sampler = IndependentSampler::new(6);
+        let mut inverse_pdfs = 0f64;
+        let samples = 20_000;
+        for _ in 0..samples {
+            let sample = triangle.sample_surface(origin, sampler.get_2d()).unwrap();
+            assert!((sample.normal - Vector::new(0f64, 0f64, 1f64)).len() < 1e-12);
+            let hit = triangle.intersects(&Ray::new(origin, sample.point - origin), (0.001, f64::INFINITY)).unwrap();
+            assert!((hit.point - sample.point).len() < 1e-9);
+            assert!((triangle.surface_pdf(origin, &hit) - sample.pdf).abs() < 1e-9 * sample.pdf);
+            inverse_pdfs += 1f64 / sample.pdf;
+        }
+        // The mean of 1 / pdf is the solid angle the triangle covers, the area times cosθ / d² = 2 / d³ somewhere
+        // between the nearest and the farthest point, 2 and about 2.16 away
+        let solid_angle = inverse_pdfs / samples as f64;
+        assert!(solid_angle > 0.5f64 * 2f64 / 2.16f64.powi(3) && solid_angle < 0.5f64 * 2f64 / 8f64, "{}", solid_angle);
+        // Nothing to pick from the plane of the triangle
+        assert!(triangle.sample_surface(Point::new(3f64, 3f64, -2f64), (0.3f64, 0.3f64)).is_none());
+    }
+}
@@ -7,15 +7,17 @@ pub struct Vector {
     pub z: f64,
 }
 
-impl Vector {
-    pub fn default() -> Self {
+impl Default for Vector {
+    fn default() -> Self {
         Vector {
             x: 0f64,
             y: 0f64,
             z: 0f64,
         }
     }
+}
 
+impl Vector {
     pub fn new(x: f64, y: f64, z: f64) -> Self {
         Vector { x, y, z }
     }
@@ -0,0 +1,137 @@
+use crate::color::rgb::RgbColor;
+use crate::ppm;
+use std::io;
+use std::path::Path;
+
+pub mod openexr;
+pub mod png;
+pub mod radiance;
+
+/// File formats a render can be written in
+///
+/// PPM and PNG hold 8-bit colours and clip everything outside [0, 1]. PFM, Radiance HDR and OpenEXR keep the full
+/// range of the render so that it can be tone mapped or composited later.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    /// Binary portable pixmap
+    Ppm,
+    /// 8-bit PNG
+    Png,
+    /// Portable float map, 32-bit floats
+    Pfm,
+    /// Radiance RGBE, a shared 8-bit exponent per pixel
+    Hdr,
+    /// OpenEXR, with 16 or 32-bit floats
+    Exr(ExrPrecision),
+}
+
+/// Size of the floats in an OpenEXR file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExrPrecision {
+    /// 16-bit floats, half the size with about 3 significant digits
+    Half,
+    /// 32-bit floats
+    Float,
+}
+
+impl ImageFormat {
+    /// Format matching the extension of the path, ignoring case. OpenEXR files are written with 32-bit floats.
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "pfm" => Some(ImageFormat::Pfm),
+            "hdr" => Some(ImageFormat::Hdr),
+            "exr" => Some(ImageFormat::Exr(ExrPrecision::Float)),
+            _ => None,
+        }
+    }
+
+    /// Whether the format keeps colours outside [0, 1]
+    pub fn is_hdr(self) -> bool {
+        !matches!(self, ImageFormat::Ppm | ImageFormat::Png)
+    }
+}
+
+/// Writes a render to a file, in the format given by the file extension
+///
+/// # Arguments
+/// * `render` - the pixels, row by row from the top left
+/// * `path` - the file to write, its extension picks the format
+/// * `dimensions` - width and height of the render
+///
+/// # Returns
+/// * an `InvalidInput` error if the extension is not one of a known format
+///
+/// # Examples
+/// ```no_run
+/// # use rusttracing::color::rgb::RgbColor;
+/// # use rusttracing::image;
+/// let render = vec![RgbColor::new(0.5, 0.5, 0.5); 16 * 9];
+/// image::save(&render, "render.png", (16, 9)).unwrap();
+/// ```
+pub fn save<P: AsRef<Path>>(render: &[RgbColor], path: P, dimensions: (u32, u32)) -> io::Result<()> {
+    let path = path.as_ref();
+    let format = ImageFormat::from_extension(path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no image format with the extension of `{}`", path.display()),
+        )
+    })?;
+    save_as(render, path, dimensions, format)
+}
+
+/// Writes a render to a file in the given format, whatever the file extension
+pub fn save_as<P: AsRef<Path>>(
+    render: &[RgbColor],
+    path: P,
+    dimensions: (u32, u32),
+    format: ImageFormat,
+) -> io::Result<()> {
+    match format {
+        ImageFormat::Ppm => ppm::generate_image(render, path, dimensions),
+        ImageFormat::Png => png::write_png(render, path, dimensions),
+        ImageFormat::Pfm => ppm::write_pfm(render, path, dimensions),
+        ImageFormat::Hdr => radiance::write_hdr(render, path, dimensions),
+        ImageFormat::Exr(precision) => openexr::write_exr(render, path, dimensions, precision),
+    }
+}
+
+// Every writer checks the buffer holds exactly one pixel per position before writing anything
+pub(crate) fn check_dimensions(render: &[RgbColor], dimensions: (u32, u32)) -> io::Result<()> {
+    if render.len() != dimensions.0 as usize * dimensions.1 as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} pixels cannot make a {}x{} image",
+                render.len(),
+                dimensions.0,
+                dimensions.1
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(ImageFormat::from_extension("render.ppm"), Some(ImageFormat::Ppm));
+        assert_eq!(ImageFormat::from_extension("out/Render.PNG"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_extension("render.pfm"), Some(ImageFormat::Pfm));
+        assert_eq!(ImageFormat::from_extension("render.hdr"), Some(ImageFormat::Hdr));
+        assert_eq!(
+            ImageFormat::from_extension("render.exr"),
+            Some(ImageFormat::Exr(ExrPrecision::Float))
+        );
+        assert_eq!(ImageFormat::from_extension("render.jpg"), None);
+        assert_eq!(ImageFormat::from_extension("render"), None);
+
+        let render = vec![RgbColor::default(); 4];
+        let path = crate::temp_path("test_format_from_extension.jpg");
+        assert_eq!(save(&render, path, (2, 2)).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::color::rgb::RgbColor;
+use crate::image::{check_dimensions, ExrPrecision};
+use exr::prelude::{f16, write_rgb_file};
+use std::io;
+use std::path::Path;
+
+/// Writes the render as an OpenEXR file with R, G and B channels of the given precision
+///
+/// # Arguments
+/// * `render` - the pixels, row by row from the top left
+/// * `path` - the file to write
+/// * `dimensions` - width and height of the render, they have to match the number of pixels
+/// * `precision` - 16 or 32-bit floats
+pub fn write_exr<P: AsRef<Path>>(
+    render: &[RgbColor],
+    path: P,
+    dimensions: (u32, u32),
+    precision: ExrPrecision,
+) -> io::Result<()> {
+    check_dimensions(render, dimensions)?;
+
+    let (width, height) = (dimensions.0 as usize, dimensions.1 as usize);
+    let pixel = |x: usize, y: usize| render[y * width + x];
+    match precision {
+        ExrPrecision::Half => write_rgb_file(path, width, height, |x, y| {
+            let c = pixel(x, y);
+            (f16::from_f64(c.x), f16::from_f64(c.y), f16::from_f64(c.z))
+        }),
+        ExrPrecision::Float => write_rgb_file(path, width, height, |x, y| {
+            let c = pixel(x, y);
+            (c.x as f32, c.y as f32, c.z as f32)
+        }),
+    }
+    .map_err(|e| match e {
+        exr::error::Error::Io(e) => e,
+        e => io::Error::other(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exr::prelude::read_first_rgba_layer_from_file;
+
+    fn round_trip(precision: ExrPrecision, name: &str) -> (Vec<RgbColor>, Vec<RgbColor>) {
+        let (width, height) = (5usize, 3usize);
+        let render: Vec<RgbColor> = (0..width * height)
+            .map(|i| RgbColor::new(i as f64 * 10f64, 0.125, 1f64 / (i as f64 + 1f64)))
+            .collect();
+        let path = crate::temp_path(name);
+        write_exr(&render, &path, (width as u32, height as u32), precision).unwrap();
+
+        let image = read_first_rgba_layer_from_file(
+            &path,
+            |resolution, _| vec![RgbColor::default(); resolution.width() * resolution.height()],
+            move |pixels: &mut Vec<RgbColor>, position, (r, g, b, _a): (f32, f32, f32, f32)| {
+                pixels[position.y() * width + position.x()] = RgbColor::new(r as f64, g as f64, b as f64)
+            },
+        )
+        .unwrap();
+        assert_eq!(image.layer_data.size.width(), width);
+        assert_eq!(image.layer_data.size.height(), height);
+        (render, image.layer_data.channel_data.pixels)
+    }
+
+    #[test]
+    fn test_exr_float_round_trip() {
+        let (render, decoded) = round_trip(ExrPrecision::Float, "test_exr_float_round_trip.exr");
+        for (pixel, decoded) in render.iter().zip(decoded) {
+            assert!((*pixel - decoded).len() <= pixel.len() * 1e-7);
+        }
+    }
+
+    #[test]
+    fn test_exr_half_round_trip() {
+        let (render, decoded) = round_trip(ExrPrecision::Half, "test_exr_half_round_trip.exr");
+        for (pixel, decoded) in render.iter().zip(decoded) {
+            // Half floats have an 11-bit mantissa
+            assert!((*pixel - decoded).len() <= pixel.len() * 1e-3);
+        }
+    }
+}
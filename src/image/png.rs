@@ -0,0 +1,61 @@
+use crate::color::rgb::RgbColor;
+use crate::image::check_dimensions;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+/// Writes the render as an 8-bit RGB PNG, clipping the colours to [0, 1]
+///
+/// # Arguments
+/// * `render` - the pixels, row by row from the top left
+/// * `path` - the file to write
+/// * `dimensions` - width and height of the render, they have to match the number of pixels
+pub fn write_png<P: AsRef<Path>>(render: &[RgbColor], path: P, dimensions: (u32, u32)) -> io::Result<()> {
+    check_dimensions(render, dimensions)?;
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, dimensions.0, dimensions.1);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = render.iter().flat_map(|pixel| pixel.to_rgb8()).collect();
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&data).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_round_trip() {
+        let (width, height) = (7u32, 3u32);
+        let render: Vec<RgbColor> = (0..width * height)
+            .map(|i| RgbColor::new(i as f64 / 20f64, 1f64 - i as f64 / 20f64, 0.5f64))
+            .collect();
+        let path = crate::temp_path("test_png_round_trip.png");
+        write_png(&render, &path, (width, height)).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0u8; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (width, height));
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        assert_eq!(info.bit_depth, png::BitDepth::Eight);
+        for (pixel, decoded) in render.iter().zip(data.chunks_exact(3)) {
+            assert_eq!(pixel.to_rgb8(), decoded);
+        }
+    }
+
+    #[test]
+    fn test_png_clips_and_checks_size() {
+        let render = vec![RgbColor::new(2f64, -1f64, 0.5f64); 4];
+        assert_eq!(render[0].to_rgb8(), [255, 0, 127]);
+        let path = crate::temp_path("test_png_clips_and_checks_size.png");
+        assert_eq!(
+            write_png(&render, path, (3, 2)).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::color::rgb::RgbColor;
+use crate::image::check_dimensions;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Writes the render as a Radiance HDR file, with flat (not run length encoded) scanlines
+///
+/// # Arguments
+/// * `render` - the pixels, row by row from the top left
+/// * `path` - the file to write
+/// * `dimensions` - width and height of the render, they have to match the number of pixels
+pub fn write_hdr<P: AsRef<Path>>(render: &[RgbColor], path: P, dimensions: (u32, u32)) -> io::Result<()> {
+    check_dimensions(render, dimensions)?;
+
+    let mut file = BufWriter::new(File::create(path)?);
+    // -Y: rows from the top down, +X: columns from left to right
+    write!(
+        file,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        dimensions.1, dimensions.0
+    )?;
+    for pixel in render {
+        file.write_all(&rgbe(*pixel))?;
+    }
+    file.flush()
+}
+
+// RGBE
+// The three channels share the exponent of the brightest one, v = m * 2^e with m in [0.5, 1). Each channel is
+// stored as an 8-bit mantissa c * 256 / 2^e and the exponent biased by 128. The dimmer channels lose precision, but
+// the eye would not see it next to the bright one. Channels beyond the largest exponent, 2^127, infinities included,
+// are clipped to the largest mantissa, and NaNs are black.
+fn rgbe(color: RgbColor) -> [u8; 4] {
+    let (r, g, b) = (color.x.max(0f64), color.y.max(0f64), color.z.max(0f64));
+    let brightest = r.max(g).max(b);
+    if brightest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    let exponent = (brightest.log2().floor() as i32).min(126) + 1;
+    let scale = 256f64 / 2f64.powi(exponent);
+    [
+        (r * scale).min(255f64) as u8,
+        (g * scale).min(255f64) as u8,
+        (b * scale).min(255f64) as u8,
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(rgbe: [u8; 4]) -> RgbColor {
+        if rgbe[3] == 0 {
+            return RgbColor::default();
+        }
+        let scale = 2f64.powi(rgbe[3] as i32 - 128 - 8);
+        RgbColor::new(rgbe[0] as f64 + 0.5, rgbe[1] as f64 + 0.5, rgbe[2] as f64 + 0.5) * scale
+    }
+
+    #[test]
+    fn test_rgbe_keeps_highlights() {
+        assert_eq!(rgbe(RgbColor::default()), [0, 0, 0, 0]);
+        assert_eq!(rgbe(RgbColor::new(1f64, 0.5, 0.25)), [128, 64, 32, 129]);
+        assert_eq!(rgbe(RgbColor::new(f64::INFINITY, 0f64, 0f64)), [255, 0, 0, 255]);
+        assert_eq!(rgbe(RgbColor::new(1e40, 1e40, 1f64)), [255, 255, 0, 255]);
+        assert_eq!(rgbe(RgbColor::new(f64::NAN, 1f64, 0.5)), [0, 128, 64, 129]);
+        for color in [
+            RgbColor::new(0.3, 0.6, 0.9),
+            RgbColor::new(12.5, 3f64, 0.75),
+            RgbColor::new(4000f64, 2000f64, 1000f64),
+        ] {
+            let decoded = decode(rgbe(color));
+            let brightest = color.x.max(color.y).max(color.z);
+            // Every channel is within one step of the shared mantissa
+            assert!((decoded - color).len() < brightest / 100f64, "{:?} became {:?}", color, decoded);
+        }
+    }
+
+    #[test]
+    fn test_write_hdr() {
+        let render = vec![RgbColor::new(2f64, 1f64, 0.5); 6];
+        let path = crate::temp_path("test_write_hdr.hdr");
+        write_hdr(&render, &path, (3, 2)).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 3\n";
+        assert!(bytes.starts_with(header));
+        assert_eq!(bytes.len(), header.len() + 6 * 4);
+        assert_eq!(&bytes[header.len()..header.len() + 4], &[128, 64, 32, 130]);
+        assert!(write_hdr(&render, &path, (2, 2)).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+pub mod color;
+pub mod geometry;
+pub mod image;
+pub mod ppm;
+pub mod scene;
+pub mod settings;
+pub mod world;
+
+// Path in the temporary directory for a file written by a test, named after the process and the test so that
+// concurrent test runs do not write over each other
+#[cfg(test)]
+pub(crate) fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("rusttracing_{}_{}", std::process::id(), name))
+}
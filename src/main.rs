@@ -1,75 +1,70 @@
-// use geometry::vec3;
-mod color;
-mod geometry;
-mod ppm;
-mod scene;
-mod world;
-
-// use crate::geometry::vec3;
-use crate::geometry::vec3;
-use color::rgb;
-use geometry::sphere::Sphere;
-use geometry::vec3::Vector as Point;
-use ppm::generate_image;
-use scene::camera::Camera;
-use scene::ray;
-use world::World;
-
-use color::antialias::AntialiasingFilter;
-use color::filter::Filter;
-use color::gamma::GammaCorrection;
-
-use color::rgb::RgbColor;
-
-use scene::material::dielectric::Dielectric;
-use scene::material::matte::Matte;
-use scene::material::metal::Metal;
+mod cli;
+
+use clap::Parser;
+use cli::Cli;
+use rusttracing::color::filter::Filter;
+use rusttracing::image;
+use rusttracing::scene::camera::Camera;
+use rusttracing::scene::description::{Scene, SceneDescription, SceneError};
+use rusttracing::settings::RenderSettings;
+use rusttracing::world::World;
 
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::Rect;
-use std::time::Duration;
 
-const IMG_NAME: &str = "render.ppm";
-const IMG_WIDTH: u32 = 400;
+pub fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(&cli) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
 
-pub fn main() -> Result<(), String> {
-    let sdl_context = sdl2::init()?;
-    let video_subsystem = sdl_context.video()?;
+fn run(cli: &Cli) -> Result<(), String> {
+    cli.validate()?;
 
-    let world = setup_world();
-    let camera = Camera::default();
-
-    let no_preview = true;
-
-    if no_preview {
-        // render(&camera, (400f64 / camera.aspect_ratio) as u32, 400);
-        let mut image = world.render(&camera, 400);
-        // Gamma correction 0->1 brighten image, gamma compression
-        // Gamma correction > 1 -> darken image, gamma expansion
-        // hopefully this oversimplified implementation is correct
-        let filter = GammaCorrection::new(0.5f64);
-        // let filter = AntialiasingFilter::new(2);
-
-        filter.apply_filter(&mut image);
-        // generate PPM
-        match generate_image(
-            &image,
-            String::from(IMG_NAME),
-            (IMG_WIDTH, (IMG_WIDTH as f64 / camera.aspect_ratio) as u32),
-        ) {
-            _ => return Ok(())
-        };
+    let mut description = load_scene(cli).map_err(|e| format!("could not load scene: {}", e))?;
+    cli.apply_to_scene(&mut description);
+    let Scene {
+        world,
+        camera,
+        mut settings,
+    } = description.build();
+    cli.apply_to_settings(&mut settings);
+
+    if cli.preview {
+        return preview(&world, &camera, &settings, &cli.filter_chain());
+    }
+
+    let mut render = world.render(&camera, &settings);
+    for filter in cli.filter_chain() {
+        filter.apply_filter(&mut render);
     }
 
+    image::save_as(
+        &render,
+        &settings.output,
+        (settings.width, settings.height),
+        cli.output_format().into(),
+    )
+    .map_err(|e| format!("could not write `{}`: {}", settings.output.display(), e))
+}
+
+// Renders the scene one scanline at a time in a window, space dumps what has been rendered so far to stdout. The
+// post-processing filters are applied to every scanline as it is rendered.
+fn preview(
+    world: &World,
+    camera: &Camera,
+    settings: &RenderSettings,
+    filters: &[Box<dyn Filter>],
+) -> Result<(), String> {
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+
     let window = video_subsystem
-        .window(
-            "rust-sdl2 demo: Video",
-            400,
-            (400 as f64 / camera.aspect_ratio) as u32,
-        )
+        .window("rust-sdl2 demo: Video", settings.width, settings.height)
         .position_centered()
         .opengl()
         .build()
@@ -79,11 +74,7 @@ pub fn main() -> Result<(), String> {
     let texture_creator = canvas.texture_creator();
 
     let mut texture = texture_creator
-        .create_texture_streaming(
-            PixelFormatEnum::RGB24,
-            400,
-            (400.0 / camera.aspect_ratio) as u32,
-        )
+        .create_texture_streaming(PixelFormatEnum::RGB24, settings.width, settings.height)
         .map_err(|e| e.to_string())?;
     // Create a red-green gradient
 
@@ -91,7 +82,7 @@ pub fn main() -> Result<(), String> {
     canvas.copy(
         &texture,
         None,
-        Some(Rect::new(0, 0, 400, (400.0 / camera.aspect_ratio) as u32)),
+        Some(Rect::new(0, 0, settings.width, settings.height)),
     )?;
     // canvas.copy_ex(
     //     &texture,
@@ -106,7 +97,10 @@ pub fn main() -> Result<(), String> {
 
     let mut event_pump = sdl_context.event_pump()?;
     let mut y = 0;
-    let img_height = (400.0 / camera.aspect_ratio) as u32;
+    let img_height = settings.height;
+    let mut sampler = settings
+        .sampler
+        .build(settings.samples_per_pixel, settings.seed.unwrap_or_else(rand::random));
     'running: loop {
         for event in event_pump.poll_iter() {
             match event {
@@ -116,12 +110,12 @@ pub fn main() -> Result<(), String> {
                     repeat: false,
                     ..
                 } => {
-                    texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
+                    texture.with_lock(None, |buffer: &mut [u8], _pitch: usize| {
                         println!("P3");
-                        println!("{} {}", 400, y);
+                        println!("{} {}", settings.width, y);
                         println!("255");
                         for x in 0..(y as usize) {
-                            for i in 0..400 {
+                            for i in 0..settings.width as usize {
                                 println!(
                                     "{} {} {}",
                                     buffer[x + i * 3],
@@ -140,17 +134,16 @@ pub fn main() -> Result<(), String> {
             continue;
         }
 
+        let mut row: Vec<_> = (0..settings.width)
+            .map(|x| world.render_pixel(camera, (x, img_height - 1 - y), settings, sampler.as_mut()))
+            .collect();
+        for filter in filters {
+            filter.apply_filter(&mut row);
+        }
         texture.with_lock(None, |buffer: &mut [u8], pitch: usize| {
-            for x in 0..(pitch / 3) {
-                let col =
-                    world.render_pixel(&camera, (x as u32, img_height - y), (400, img_height));
+            for (x, col) in row.iter().enumerate() {
                 let offset = y as usize * pitch + x * 3;
-                if offset >= 270000 {
-                    println!("{} {}", y, img_height);
-                }
-                buffer[offset] = (255.999 * col.x) as u8;
-                buffer[offset + 1] = (255.999 * col.y) as u8;
-                buffer[offset + 2] = (255.999 * col.z) as u8;
+                buffer[offset..offset + 3].copy_from_slice(&col.to_rgb8());
             }
         })?;
         y += 1;
@@ -162,48 +155,12 @@ pub fn main() -> Result<(), String> {
     Ok(())
 }
 
-fn setup_world() -> World {
-    // let vec = vec3::Vector::default();
-    // println!("{:?}", vec);
-    // sample_gen(256, 256);
-    // Create our virtual world
-    let mut world = World::new();
-
-    let ball1 = Sphere::new(
-        Point::new(0f64, 0f64, -1f64),
-        0.5f64,
-        Box::new(Matte::new(RgbColor::new(0.8f64, 0.8f64, 0.8f64))),
-    );
-    let ball2 = Sphere::new(
-        Point::new(-1f64, 0f64, -1f64),
-        0.5f64,
-        Box::new(Metal::new(RgbColor::new(0.8f64, 0.8f64, 0.8f64), 0.5)),
-    );
-    let ball3 = Sphere::new(
-        Point::new(1f64, 0f64, -1f64),
-        0.5f64,
-        Box::new(Dielectric::new(RgbColor::new(0.9f64, 0.9f64, 0.9f64), 1f64)),
-    );
-    // let ball2 = Sphere::new(Point::new(1f64, 1f64, -3f64), 1f64);
-    let ground = Sphere::new(
-        Point::new(0f64, -100.5f64, -1f64),
-        100f64,
-        Box::new(Matte::new(RgbColor::new(0.2f64, 0.8f64, 0.8f64))),
-    );
-    world.add(Box::new(ground));
-    world.add(Box::new(ball1));
-    world.add(Box::new(ball2));
-    world.add(Box::new(ball3));
-    // world.add(Box::new(ball2));
-
-    return world;
-
-    // Image
-    // let aspect_ratio = 16f64/9f64;
-    // let image_width = 400;
-    // let image_height = (image_width as f64 / aspect_ratio) as u32;
-
-    // create a camera at 0,0,0 with a 16:9 aspect ratio and focal length of 1u
-    let camera = Camera::default();
-    eprintln!("{:#?}", camera);
+// Scene rendered when no scene file is given on the command line
+const DEFAULT_SCENE: &str = include_str!("../scenes/default.toml");
+
+fn load_scene(cli: &Cli) -> Result<SceneDescription, SceneError> {
+    match &cli.scene {
+        Some(path) => SceneDescription::load(path),
+        None => SceneDescription::from_toml(DEFAULT_SCENE),
+    }
 }
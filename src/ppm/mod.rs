@@ -1,30 +1,383 @@
 use crate::color::rgb::RgbColor;
-use std::fs::File;
+use crate::image::check_dimensions;
+use std::fmt;
+use std::fs::{self, File};
 use std::io::prelude::*;
+use std::io::{self, BufWriter};
+use std::path::Path;
 
-pub fn generate_image(
-    render: &Vec<RgbColor>,
-    filename: String,
-    dimensions: (u32, u32),
-) -> std::io::Result<()> {
-    let mut file = File::create(filename)?;
-    file.write_all(b"P3\n")?;
-    file.write_all(format!("{} {}", dimensions.0, dimensions.1).as_bytes())?;
-    file.write_all(b"\n255\n")?;
+/// Writes the render as a binary (P6) portable pixmap with 8-bit channels, clipping the colours to [0, 1]
+///
+/// # Arguments
+/// * `render` - the pixels, row by row from the top left
+/// * `path` - the file to write
+/// * `dimensions` - width and height of the render, they have to match the number of pixels
+///
+/// # Examples
+/// ```no_run
+/// # use rusttracing::color::rgb::RgbColor;
+/// # use rusttracing::ppm;
+/// let render = vec![RgbColor::new(0.5, 0.5, 0.5); 16 * 9];
+/// ppm::generate_image(&render, "render.ppm", (16, 9)).unwrap();
+/// ```
+pub fn generate_image<P: AsRef<Path>>(render: &[RgbColor], path: P, dimensions: (u32, u32)) -> io::Result<()> {
+    check_dimensions(render, dimensions)?;
+
+    let mut file = BufWriter::new(File::create(path)?);
+    write!(file, "P6\n{} {}\n255\n", dimensions.0, dimensions.1)?;
     for pixel in render {
-        file.write_all(format!("{}\n", pixel).as_bytes())?;
+        file.write_all(&pixel.to_rgb8())?;
+    }
+    file.flush()
+}
+
+/// Writes the render as a colour portable float map, 32-bit floats that keep the full range of the render
+///
+/// # Arguments
+/// * `render` - the pixels, row by row from the top left
+/// * `path` - the file to write
+/// * `dimensions` - width and height of the render, they have to match the number of pixels
+pub fn write_pfm<P: AsRef<Path>>(render: &[RgbColor], path: P, dimensions: (u32, u32)) -> io::Result<()> {
+    check_dimensions(render, dimensions)?;
+
+    let mut file = BufWriter::new(File::create(path)?);
+    // A negative scale marks little endian floats
+    write!(file, "PF\n{} {}\n-1.0\n", dimensions.0, dimensions.1)?;
+    // Rows go from the bottom of the image up
+    for row in render.chunks_exact(dimensions.0.max(1) as usize).rev() {
+        for pixel in row {
+            for channel in [pixel.x, pixel.y, pixel.z] {
+                file.write_all(&(channel as f32).to_le_bytes())?;
+            }
+        }
+    }
+    file.flush()
+}
+
+#[derive(Debug)]
+pub enum PpmError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not a PPM, PGM or PFM image, or its header is malformed
+    Header(String),
+    /// The file holds fewer or more samples than its header says
+    Dimensions {
+        width: u32,
+        height: u32,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for PpmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PpmError::Io(e) => write!(f, "{}", e),
+            PpmError::Header(message) => write!(f, "bad header: {}", message),
+            PpmError::Dimensions {
+                width,
+                height,
+                expected,
+                found,
+            } => write!(
+                f,
+                "a {}x{} image needs {} samples, the file has {}",
+                width, height, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PpmError {}
+
+impl From<io::Error> for PpmError {
+    fn from(e: io::Error) -> Self {
+        PpmError::Io(e)
     }
+}
+
+/// Reads a portable pixmap (P3 and P6), graymap (P2 and P5) or float map (PF and Pf) into a render buffer
+///
+/// Integer samples are scaled to [0, 1] by the maximum value of the file, grey images are read with the same value in
+/// every channel.
+///
+/// # Returns
+/// * the pixels, row by row from the top left, and the width and height of the image
+///
+/// # Examples
+/// ```no_run
+/// # use rusttracing::ppm;
+/// let (pixels, (width, height)) = ppm::read_image("texture.ppm").unwrap();
+/// assert_eq!(pixels.len(), (width * height) as usize);
+/// ```
+pub fn read_image<P: AsRef<Path>>(path: P) -> Result<(Vec<RgbColor>, (u32, u32)), PpmError> {
+    decode(&fs::read(path)?)
+}
+
+/// Reads an image to be used as a texture, in linear light like the rest of the renderer
+///
+/// PPM and PGM images are taken to be sRGB encoded, as they are when written by any image editor, and decoded. PFM
+/// images already hold linear values and are left alone.
+pub fn read_texture<P: AsRef<Path>>(path: P) -> Result<(Vec<RgbColor>, (u32, u32)), PpmError> {
+    let bytes = fs::read(path)?;
+    let (mut render, dimensions) = decode(&bytes)?;
+    if !bytes.starts_with(b"PF") && !bytes.starts_with(b"Pf") {
+        render.iter_mut().for_each(|pixel| *pixel = pixel.to_linear());
+    }
+    Ok((render, dimensions))
+}
 
-    Ok(())
+// Reads the whitespace separated header fields, skipping comments
+struct Header<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Header<'_> {
+    fn field(&mut self) -> Result<&str, PpmError> {
+        loop {
+            match self.bytes.get(self.offset) {
+                Some(b'#') => {
+                    while self.bytes.get(self.offset).is_some_and(|&b| b != b'\n') {
+                        self.offset += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.offset += 1,
+                Some(_) => break,
+                None => return Err(PpmError::Header("unexpected end of file".to_string())),
+            }
+        }
+        let start = self.offset;
+        while self.bytes.get(self.offset).is_some_and(|b| !b.is_ascii_whitespace()) {
+            self.offset += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.offset])
+            .map_err(|_| PpmError::Header("the header is not text".to_string()))
+    }
+
+    fn number<T: std::str::FromStr>(&mut self, name: &str) -> Result<T, PpmError> {
+        let field = self.field()?;
+        field
+            .parse()
+            .map_err(|_| PpmError::Header(format!("`{}` is not a valid {}", field, name)))
+    }
+
+    // The binary data starts after the single whitespace character ending the header
+    fn data(&self) -> &[u8] {
+        self.bytes.get(self.offset + 1..).unwrap_or(&[])
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<(Vec<RgbColor>, (u32, u32)), PpmError> {
+    let mut header = Header { bytes, offset: 0 };
+    let magic = header.field()?.to_string();
+    let width: u32 = header.number("width")?;
+    let height: u32 = header.number("height")?;
+    let pixels = (width as usize).saturating_mul(height as usize);
+
+    // 1. Samples per pixel, 3 for colour and 1 for grey
+    let channels = match magic.as_str() {
+        "P3" | "P6" | "PF" => 3,
+        "P2" | "P5" | "Pf" => 1,
+        _ => return Err(PpmError::Header(format!("unsupported format `{}`", magic))),
+    };
+    // Saturated for headers too large for any file, which then never match the data
+    let expected = pixels.saturating_mul(channels);
+    let dimensions_error = |found: usize| PpmError::Dimensions {
+        width,
+        height,
+        expected,
+        found,
+    };
+
+    // 2. Read the samples, scaled to [0, 1] for the integer formats
+    let samples: Vec<f64> = match magic.as_str() {
+        "PF" | "Pf" => {
+            let scale: f64 = header.number("scale")?;
+            let data = header.data();
+            if data.len() != expected.saturating_mul(4) {
+                return Err(dimensions_error(data.len() / 4));
+            }
+            let mut samples: Vec<f64> = data
+                .chunks_exact(4)
+                .map(|b| {
+                    let b = [b[0], b[1], b[2], b[3]];
+                    if scale < 0f64 {
+                        f32::from_le_bytes(b) as f64
+                    } else {
+                        f32::from_be_bytes(b) as f64
+                    }
+                })
+                .collect();
+            // Float maps store the bottom row first
+            if width > 0 {
+                let row = width as usize * channels;
+                samples = samples.chunks_exact(row).rev().flatten().copied().collect();
+            }
+            samples
+        }
+        _ => {
+            let max: u32 = header.number("maximum value")?;
+            if max == 0 || max > u16::MAX as u32 {
+                return Err(PpmError::Header(format!("maximum value {} is not between 1 and 65535", max)));
+            }
+            let scale = 1f64 / max as f64;
+            if magic == "P3" || magic == "P2" {
+                // Grown as the samples are read, the header alone may promise more than there is memory for
+                let mut samples = Vec::new();
+                while header.offset < bytes.len() && samples.len() <= expected {
+                    match header.field() {
+                        Ok(field) => samples.push(
+                            field
+                                .parse::<u32>()
+                                .map_err(|_| PpmError::Header(format!("`{}` is not a sample", field)))?
+                                as f64
+                                * scale,
+                        ),
+                        // Trailing whitespace
+                        Err(_) => break,
+                    }
+                }
+                if samples.len() != expected {
+                    return Err(dimensions_error(samples.len()));
+                }
+                samples
+            } else {
+                // Two bytes per sample, most significant first, when the maximum does not fit in one
+                let size = if max > 255 { 2 } else { 1 };
+                let data = header.data();
+                if data.len() != expected.saturating_mul(size) {
+                    return Err(dimensions_error(data.len() / size));
+                }
+                data.chunks_exact(size)
+                    .map(|b| match *b {
+                        [v] => v as f64 * scale,
+                        _ => u16::from_be_bytes([b[0], b[1]]) as f64 * scale,
+                    })
+                    .collect()
+            }
+        }
+    };
+
+    // 3. Gather the samples into pixels
+    let render = match channels {
+        3 => samples
+            .chunks_exact(3)
+            .map(|c| RgbColor::new(c[0], c[1], c[2]))
+            .collect(),
+        _ => samples.iter().map(|&g| RgbColor::new(g, g, g)).collect(),
+    };
+    Ok((render, (width, height)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::color::gamma::srgb_to_linear;
+
+    fn gradient(width: u32, height: u32) -> Vec<RgbColor> {
+        (0..width * height)
+            .map(|i| RgbColor::new(i as f64 / 255f64, 1f64 - i as f64 / 255f64, 2f64))
+            .collect()
+    }
+
+    #[test]
+    fn test_generate_image_round_trip() {
+        let render = gradient(5, 4);
+        let path = crate::temp_path("test_generate_image_round_trip.ppm");
+        generate_image(&render, &path, (5, 4)).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"P6\n5 4\n255\n"));
+        assert_eq!(bytes.len(), b"P6\n5 4\n255\n".len() + 5 * 4 * 3);
+
+        let (read, dimensions) = read_image(&path).unwrap();
+        assert_eq!(dimensions, (5, 4));
+        for (pixel, read) in render.iter().zip(read) {
+            // Clipped to 1 and quantised to 8 bits
+            assert!((pixel.x - read.x).abs() < 1f64 / 255f64);
+            assert_eq!(read.z, 1f64);
+        }
+        assert!(generate_image(&render, &path, (4, 4)).is_err());
+    }
+
+    #[test]
+    fn test_pfm_round_trip() {
+        let render = gradient(3, 2);
+        let path = crate::temp_path("test_pfm_round_trip.pfm");
+        write_pfm(&render, &path, (3, 2)).unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(b"PF\n3 2\n-1.0\n"));
+        // Colours brighter than 1 come back unchanged, rows in the same order
+        let (read, dimensions) = read_image(&path).unwrap();
+        assert_eq!(dimensions, (3, 2));
+        for (pixel, read) in render.iter().zip(read) {
+            assert!((*pixel - read).len() < 1e-6);
+        }
+        assert!(write_pfm(&render, &path, (3, 3)).is_err());
+    }
+
+    #[test]
+    fn test_read_ascii_and_grey() {
+        let (render, dimensions) = decode(b"P3\n# a comment\n2 1\n255\n255 0 0\n0 0 255\n").unwrap();
+        assert_eq!(dimensions, (2, 1));
+        assert_eq!(render, vec![RgbColor::new(1f64, 0f64, 0f64), RgbColor::new(0f64, 0f64, 1f64)]);
+
+        let (render, _) = decode(b"P2 2 1 4 1 4").unwrap();
+        assert_eq!(render, vec![RgbColor::new(0.25, 0.25, 0.25), RgbColor::new(1f64, 1f64, 1f64)]);
+
+        let (render, _) = decode(b"P5 1 1 65535\n\x80\x00").unwrap();
+        assert!((render[0].x - 32768f64 / 65535f64).abs() < 1e-12);
+
+        let mut grey_float = b"Pf\n1 2\n1.0\n".to_vec();
+        grey_float.extend(0.5f32.to_be_bytes());
+        grey_float.extend(3f32.to_be_bytes());
+        let (render, _) = decode(&grey_float).unwrap();
+        assert_eq!(render, vec![RgbColor::new(3f64, 3f64, 3f64), RgbColor::new(0.5, 0.5, 0.5)]);
+    }
+
+    #[test]
+    fn test_read_texture_is_linear() {
+        let render = vec![RgbColor::new(0.5, 2f64, 0.25)];
+        let path = crate::temp_path("test_read_texture_is_linear.ppm");
+        generate_image(&render, &path, (1, 1)).unwrap();
+        let (texture, _) = read_texture(&path).unwrap();
+        assert!((texture[0].x - srgb_to_linear(127f64 / 255f64)).abs() < 1e-12);
+        assert_eq!(texture[0].y, 1f64);
+
+        let path = crate::temp_path("test_read_texture_is_linear.pfm");
+        write_pfm(&render, &path, (1, 1)).unwrap();
+        let (texture, _) = read_texture(&path).unwrap();
+        assert_eq!(texture[0], render[0]);
+    }
 
     #[test]
-    fn test_generate_image() {
-        let render = vec![RgbColor::default(); 1600];
-        assert!(generate_image(&render, "test.ppm".to_string(), (40, 40)).is_ok());
+    fn test_read_errors() {
+        assert!(matches!(decode(b"P4\n1 1\n"), Err(PpmError::Header(_))));
+        assert!(matches!(decode(b"P6\n1 x\n255\n"), Err(PpmError::Header(_))));
+        assert!(matches!(decode(b"P6\n1 1\n0\n"), Err(PpmError::Header(_))));
+        match decode(b"P6\n2 2\n255\n\x01\x02\x03") {
+            Err(PpmError::Dimensions {
+                expected: 12, found: 3, ..
+            }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match decode(b"P3\n1 1\n255\n1 2 3 4\n") {
+            Err(PpmError::Dimensions { expected: 3, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        // Headers promising far more samples than the file holds
+        match decode(b"P3 99999 99999 255\n1 2 3\n") {
+            Err(PpmError::Dimensions {
+                expected: 29999400003,
+                found: 3,
+                ..
+            }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(decode(b"P6 4294967295 4294967295 255\n\x01\x02\x03"), Err(PpmError::Dimensions { .. })));
+        assert!(matches!(decode(b"PF 4294967295 4294967295 -1\n\x00\x00\x00\x00"), Err(PpmError::Dimensions { .. })));
+        assert!(matches!(
+            read_image(crate::temp_path("test_read_errors.ppm")),
+            Err(PpmError::Io(_))
+        ));
     }
 }
@@ -0,0 +1,34 @@
+use crate::color::rgb::RgbColor;
+use crate::scene::ray::Ray;
+
+/// Light arriving from far away, seen by every ray that leaves the world without hitting anything
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Background {
+    /// Vertical gradient, blending linearly from `bottom` for rays going straight down to `top` for rays going
+    /// straight up
+    Gradient { bottom: RgbColor, top: RgbColor },
+    /// The same colour in every direction, black for scenes lit only by their emitters
+    Solid(RgbColor),
+}
+
+impl Background {
+    pub fn color(&self, ray: &Ray) -> RgbColor {
+        match *self {
+            Background::Gradient { bottom, top } => {
+                let t: f64 = 0.5 * (ray.direction.y + 1f64);
+                bottom * (1f64 - t) + top * t
+            }
+            Background::Solid(color) => color,
+        }
+    }
+}
+
+impl Default for Background {
+    // Blue sky
+    fn default() -> Self {
+        Background::Gradient {
+            bottom: RgbColor::new(0.75f64, 0.85f64, 1f64),
+            top: RgbColor::new(0.25f64, 0.55f64, 1f64),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use crate::geometry::aabb::{axis, Aabb};
+use crate::geometry::vec3::Vector;
+use crate::scene::intersect::{Intersect, Intersection};
+use crate::scene::ray::Ray;
+
+/// Bounding volume hierarchy over the objects of a world
+///
+/// A binary tree of bounding boxes, each node enclosing all the objects below it. A ray that misses a node's box
+/// cannot hit anything inside it, so the whole subtree is skipped, bringing the cost of finding the closest hit down
+/// from O(n) to roughly O(log n).
+///
+/// The tree only stores indices into the object list it was built from, the same list has to be passed back in
+/// when tracing rays through it.
+#[derive(Debug)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    // Object indices, reordered so that every leaf refers to a contiguous range
+    indices: Vec<usize>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Leaf { bbox: Aabb, start: usize, count: usize },
+    Interior { bbox: Aabb, left: usize, right: usize },
+}
+
+impl Node {
+    fn bbox(&self) -> &Aabb {
+        match self {
+            Node::Leaf { bbox, .. } | Node::Interior { bbox, .. } => bbox,
+        }
+    }
+}
+
+// Bookkeeping for an object while the tree is built
+struct BuildItem {
+    index: usize,
+    bbox: Aabb,
+    centroid: Vector,
+}
+
+impl Bvh {
+    // Leaves with this many objects or fewer are never split
+    const MAX_LEAF_SIZE: usize = 2;
+    // Number of candidate split planes evaluated per node
+    const SAH_BUCKETS: usize = 12;
+    // Cost of testing a ray against a node box relative to testing it against an object
+    const TRAVERSAL_COST: f64 = 0.125;
+
+    /// Builds the hierarchy over the input objects using the surface area heuristic to choose the splits
+    pub fn new(objects: &[Box<dyn Intersect>]) -> Self {
+        Self::from_boxes(objects.iter().map(|object| object.bounding_box()))
+    }
+
+    /// Builds the hierarchy over anything with a bounding box, the items are then told apart by their position in
+    /// `boxes` and traced with `hit_with` and `any_hit_with`
+    pub fn from_boxes(boxes: impl ExactSizeIterator<Item = Aabb>) -> Self {
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * boxes.len()),
+            indices: Vec::with_capacity(boxes.len()),
+        };
+        let mut items: Vec<BuildItem> = boxes
+            .enumerate()
+            .map(|(index, bbox)| BuildItem {
+                index,
+                bbox,
+                centroid: bbox.centroid(),
+            })
+            .collect();
+        if !items.is_empty() {
+            bvh.build(&mut items);
+        }
+        bvh
+    }
+
+    // Recursively builds the subtree over `items` and returns the index of its root node
+    fn build(&mut self, items: &mut [BuildItem]) -> usize {
+        let bbox = items.iter().fold(Aabb::empty(), |b, item| Aabb::union(b, item.bbox));
+        let node = self.nodes.len();
+
+        match Self::split(items, &bbox) {
+            Some(mid) => {
+                // Reserve the slot of the interior node, its children are only known once they are built
+                self.nodes.push(Node::Leaf { bbox, start: 0, count: 0 });
+                let (left_items, right_items) = items.split_at_mut(mid);
+                let left = self.build(left_items);
+                let right = self.build(right_items);
+                self.nodes[node] = Node::Interior { bbox, left, right };
+            }
+            None => {
+                let start = self.indices.len();
+                self.indices.extend(items.iter().map(|item| item.index));
+                self.nodes.push(Node::Leaf {
+                    bbox,
+                    start,
+                    count: items.len(),
+                });
+            }
+        }
+        node
+    }
+
+    // Surface area heuristic
+    // The probability of a ray hitting a child box, given it hit the parent box, is the ratio of their surface areas.
+    // The expected cost of a split is then
+    // C = C_trav + SA(L)/SA(P) * N(L) + SA(R)/SA(P) * N(R)
+    // The centroids are binned into buckets along the longest axis and the cheapest bucket boundary is picked. The
+    // items are partitioned around it and the index of the first item on the right is returned, or None when keeping
+    // all the items in a single leaf is cheaper.
+    fn split(items: &mut [BuildItem], bbox: &Aabb) -> Option<usize> {
+        if items.len() <= Self::MAX_LEAF_SIZE {
+            return None;
+        }
+
+        let centroid_bounds = items
+            .iter()
+            .fold(Aabb::empty(), |b, item| Aabb::grow(b, item.centroid));
+        let split_axis = centroid_bounds.longest_axis();
+        let lo = axis(centroid_bounds.min, split_axis);
+        let extent = axis(centroid_bounds.extent(), split_axis);
+        if extent <= 0f64 {
+            // All the centroids are in the same spot, there is nothing to split on
+            return None;
+        }
+
+        let bucket_of = |item: &BuildItem| {
+            let b = ((axis(item.centroid, split_axis) - lo) / extent * Self::SAH_BUCKETS as f64) as usize;
+            b.min(Self::SAH_BUCKETS - 1)
+        };
+
+        let mut counts = [0usize; Self::SAH_BUCKETS];
+        let mut bounds = [Aabb::empty(); Self::SAH_BUCKETS];
+        for item in items.iter() {
+            let b = bucket_of(item);
+            counts[b] += 1;
+            bounds[b] = Aabb::union(bounds[b], item.bbox);
+        }
+
+        // Cost of splitting after each bucket, sweeping from both ends to accumulate the child boxes
+        let mut best: Option<(usize, f64)> = None;
+        for boundary in 1..Self::SAH_BUCKETS {
+            let (left_count, left_box) = (0..boundary).fold((0, Aabb::empty()), |(n, b), i| {
+                (n + counts[i], Aabb::union(b, bounds[i]))
+            });
+            let (right_count, right_box) = (boundary..Self::SAH_BUCKETS)
+                .fold((0, Aabb::empty()), |(n, b), i| (n + counts[i], Aabb::union(b, bounds[i])));
+            if left_count == 0 || right_count == 0 {
+                continue;
+            }
+            let cost = Self::TRAVERSAL_COST
+                + (left_box.surface_area() * left_count as f64 + right_box.surface_area() * right_count as f64)
+                    / bbox.surface_area();
+            if best.is_none_or(|(_, best_cost)| cost < best_cost) {
+                best = Some((boundary, cost));
+            }
+        }
+
+        let (boundary, cost) = best?;
+        if cost >= items.len() as f64 && items.len() <= 4 * Self::MAX_LEAF_SIZE {
+            return None;
+        }
+
+        // Partition the items in place around the chosen bucket boundary
+        let mut mid = 0;
+        for i in 0..items.len() {
+            if bucket_of(&items[i]) < boundary {
+                items.swap(i, mid);
+                mid += 1;
+            }
+        }
+        Some(mid)
+    }
+
+    /// Returns the closest intersection of the ray with the objects the hierarchy was built from, within the given
+    /// range, along with the index of the object hit
+    ///
+    /// # Arguments
+    /// * `objects` - The objects the hierarchy was built from
+    /// * `ray` - Ref to the input ray
+    /// * `range` - The range within which the search for intersection will happen
+    pub fn hit(&self, objects: &[Box<dyn Intersect>], ray: &Ray, range: (f64, f64)) -> Option<(Intersection, usize)> {
+        self.hit_with(ray, range, |index, range| objects[index].intersects(ray, range))
+    }
+
+    /// Returns the closest intersection of the ray within the given range, along with the index of the item hit,
+    /// testing the ray against an item with `intersects`
+    ///
+    /// # Arguments
+    /// * `ray` - Ref to the input ray
+    /// * `range` - The range within which the search for intersection will happen
+    /// * `intersects` - The intersection of the ray with the item at an index within a range, if any
+    pub fn hit_with(
+        &self,
+        ray: &Ray,
+        range: (f64, f64),
+        intersects: impl Fn(usize, (f64, f64)) -> Option<Intersection>,
+    ) -> Option<(Intersection, usize)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let inv_direction = Vector::new(1f64 / ray.direction.x, 1f64 / ray.direction.y, 1f64 / ray.direction.z);
+        let mut closest: Option<(Intersection, usize)> = None;
+        let mut closest_t = range.1;
+        let mut stack = vec![0usize];
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            // Anything beyond the closest hit found so far cannot be the closest one
+            if !node.bbox().hit(ray, &inv_direction, (range.0, closest_t)) {
+                continue;
+            }
+            match *node {
+                Node::Leaf { start, count, .. } => {
+                    for &index in &self.indices[start..start + count] {
+                        if let Some(intr) = intersects(index, (range.0, closest_t)) {
+                            closest_t = intr.t;
+                            closest = Some((intr, index));
+                        }
+                    }
+                }
+                Node::Interior { left, right, .. } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+
+        closest
+    }
+
+    /// Returns whether the ray hits any of the objects the hierarchy was built from within the given range, stopping
+    /// at the first one found. Cheaper than `hit` for shadow rays, which only need to know whether something is in
+    /// the way.
+    ///
+    /// # Arguments
+    /// * `objects` - The objects the hierarchy was built from
+    /// * `ray` - Ref to the input ray
+    /// * `range` - The range within which the search for intersection will happen
+    pub fn any_hit(&self, objects: &[Box<dyn Intersect>], ray: &Ray, range: (f64, f64)) -> bool {
+        self.any_hit_with(ray, range, |index| objects[index].occludes(ray, range))
+    }
+
+    /// Returns whether `occludes` is true for any item whose box the ray passes through within the given range
+    ///
+    /// # Arguments
+    /// * `ray` - Ref to the input ray
+    /// * `range` - The range within which the search for intersection will happen
+    /// * `occludes` - Whether the item at an index is in the way of the ray within the range
+    pub fn any_hit_with(&self, ray: &Ray, range: (f64, f64), occludes: impl Fn(usize) -> bool) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let inv_direction = Vector::new(1f64 / ray.direction.x, 1f64 / ray.direction.y, 1f64 / ray.direction.z);
+        let mut stack = vec![0usize];
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if !node.bbox().hit(ray, &inv_direction, range) {
+                continue;
+            }
+            match *node {
+                Node::Leaf { start, count, .. } => {
+                    if self.indices[start..start + count]
+                        .iter()
+                        .any(|&index| occludes(index))
+                    {
+                        return true;
+                    }
+                }
+                Node::Interior { left, right, .. } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+
+        false
+    }
+}
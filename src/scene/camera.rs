@@ -1,3 +1,4 @@
+use crate::geometry::sampling;
 use crate::geometry::vec3::Vector;
 use crate::geometry::vec3::Vector as Point;
 use crate::scene::ray::Ray;
@@ -9,6 +10,15 @@ pub struct Camera {
     bottom_left_corner: Point,
     horizontal: Vector,
     vertical: Vector,
+    // Orthonormal basis of the camera, u points right, v up and w backwards, away from what the camera looks at
+    u: Vector,
+    v: Vector,
+    w: Vector,
+    // Thin lens, a lens radius of 0 is a pinhole camera with everything in focus
+    lens_radius: f64,
+    // Distance to the plane in focus, relative to the distance to the viewport
+    focus_ratio: f64,
+    focal_length: f64,
 }
 
 impl Camera {
@@ -19,32 +29,104 @@ impl Camera {
     //                          |
     // pos ---focal_length--- viewport
     pub fn new(pos: Point, aspect_ratio: f64, focal_length: f64) -> Self {
-        let viewport_height = 2;
-        let viewport_width = aspect_ratio * viewport_height as f64;
-
-        let origin = pos;
-        let horizontal = Vector::new(viewport_width, 0f64, 0f64);
-        let vertical = Vector::new(0f64, viewport_height as f64, 0f64);
-        let bottom_left_corner =
-            origin - horizontal / 2f64 - vertical / 2f64 - Vector::new(0f64, 0f64, focal_length);
+        // Looking down -z with y up and a viewport of height 2
+        Camera::with_basis(
+            pos,
+            (
+                Vector::new(1f64, 0f64, 0f64),
+                Vector::new(0f64, 1f64, 0f64),
+                Vector::new(0f64, 0f64, 1f64),
+            ),
+            2f64,
+            aspect_ratio,
+            focal_length,
+        )
+    }
+
+    // Positionable camera
+    // The camera sits at `look_from` and looks towards `look_at`. The view direction and the `vup` vector span the
+    // vertical plane of the camera, which fixes the roll around the view direction.
+    //          v   vup
+    //          |  /
+    //          | /
+    //          |/______ u
+    //         / look_from
+    //        /
+    //       w       (look_at is along -w)
+    // The viewport is 1 unit in front of the camera, so for a vertical field of view θ its height is 2*tan(θ/2)
+    pub fn look_at(look_from: Point, look_at: Point, vup: Vector, vfov: f64, aspect_ratio: f64) -> Self {
+        let w = Vector::unit(look_from - look_at);
+        let u = Vector::unit(Vector::cross(vup, w));
+        let v = Vector::cross(w, u);
+
+        let viewport_height = 2f64 * f64::tan(vfov.to_radians() / 2f64);
+        Camera::with_basis(look_from, (u, v, w), viewport_height, aspect_ratio, 1f64)
+    }
+
+    fn with_basis(
+        origin: Point,
+        (u, v, w): (Vector, Vector, Vector),
+        viewport_height: f64,
+        aspect_ratio: f64,
+        focal_length: f64,
+    ) -> Self {
+        let viewport_width = aspect_ratio * viewport_height;
+
+        let horizontal = u * viewport_width;
+        let vertical = v * viewport_height;
+        let bottom_left_corner = origin - horizontal / 2f64 - vertical / 2f64 - w * focal_length;
         Camera {
             aspect_ratio,
             origin,
             bottom_left_corner,
             horizontal,
             vertical,
+            u,
+            v,
+            w,
+            lens_radius: 0f64,
+            focus_ratio: 1f64,
+            focal_length,
         }
     }
 
-    pub fn default() -> Self {
-        Camera::new(Point::new(0f64, 0f64, 0f64), 16f64 / 9f64, 1f64)
+    // Thin lens approximation
+    // Rays leave from a random point on a disk of radius `aperture_radius` around the camera origin, in the u-v plane,
+    // instead of from the origin itself. They are all aimed at the point the pinhole ray would cross the plane
+    // `focus_distance` away from the camera, so objects on that plane stay sharp and the rest gets blurred, more so
+    // the further they are from it and the larger the aperture.
+    //        lens
+    //         |\
+    //  origin |  \ ______ focus plane
+    //         |  /
+    //         |/
+    pub fn with_lens(self, aperture_radius: f64, focus_distance: f64) -> Self {
+        Camera {
+            lens_radius: aperture_radius,
+            focus_ratio: focus_distance / self.focal_length,
+            ..self
+        }
     }
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
-        Ray::new(
-            self.origin,
-            self.bottom_left_corner + self.horizontal * u + self.vertical * v - self.origin,
-        )
+    /// Returns the ray from the camera through the point (s, t) of the viewport, where (0, 0) is the bottom left
+    /// corner and (1, 1) the top right one. With an aperture the ray starts from the point of the lens picked by the
+    /// uniform 2D sample `lens`.
+    pub fn get_ray(&self, s: f64, t: f64, lens: (f64, f64)) -> Ray {
+        let direction = self.bottom_left_corner + self.horizontal * s + self.vertical * t - self.origin;
+        if self.lens_radius == 0f64 {
+            return Ray::new(self.origin, direction);
+        }
+
+        let (x, y) = sampling::unit_disk(lens);
+        let offset = self.u * (x * self.lens_radius) + self.v * (y * self.lens_radius);
+        // The pinhole ray crosses the focus plane at origin + direction * focus_ratio, aim there from the lens
+        Ray::new(self.origin + offset, direction - offset / self.focus_ratio)
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new(Point::new(0f64, 0f64, 0f64), 16f64 / 9f64, 1f64)
     }
 }
 
@@ -57,11 +139,102 @@ mod tests {
         assert_eq!(
             Camera::default(),
             Camera {
+                aspect_ratio: 16f64 / 9f64,
                 origin: Point::new(0f64, 0f64, 0f64),
-                bottom_left_corner: Vector::new(-16 as f64 / 9 as f64, -1f64, -1f64),
-                horizontal: Vector::new(2f64 * 16 as f64 / 9 as f64, 0f64, 0f64),
-                vertical: Vector::new(0f64, 2f64, 0f64)
+                bottom_left_corner: Vector::new(-16f64 / 9f64, -1f64, -1f64),
+                horizontal: Vector::new(2f64 * 16f64 / 9f64, 0f64, 0f64),
+                vertical: Vector::new(0f64, 2f64, 0f64),
+                u: Vector::new(1f64, 0f64, 0f64),
+                v: Vector::new(0f64, 1f64, 0f64),
+                w: Vector::new(0f64, 0f64, 1f64),
+                lens_radius: 0f64,
+                focus_ratio: 1f64,
+                focal_length: 1f64,
             }
         );
     }
+
+    fn assert_close(a: Vector, b: Vector) {
+        assert!((a - b).len() < 1e-12, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_camera_look_at_matches_default() {
+        // A 90 degree field of view gives the same viewport of height 2 at distance 1
+        let camera = Camera::look_at(
+            Point::new(0f64, 0f64, 0f64),
+            Point::new(0f64, 0f64, -1f64),
+            Vector::new(0f64, 1f64, 0f64),
+            90f64,
+            16f64 / 9f64,
+        );
+        let default = Camera::default();
+        for (s, t) in [(0f64, 0f64), (0.5f64, 0.5f64), (1f64, 0.25f64), (0.3f64, 1f64)] {
+            assert_close(camera.get_ray(s, t, (0.5, 0.5)).direction, default.get_ray(s, t, (0.5, 0.5)).direction);
+            assert_eq!(camera.get_ray(s, t, (0.5, 0.5)).origin, default.get_ray(s, t, (0.5, 0.5)).origin);
+        }
+    }
+
+    #[test]
+    fn test_camera_look_at_basis() {
+        let look_from = Point::new(3f64, 3f64, 2f64);
+        let look_at = Point::new(0f64, 0f64, -1f64);
+        let camera = Camera::look_at(look_from, look_at, Vector::new(0f64, 1f64, 0f64), 20f64, 2f64);
+
+        // The centre of the viewport is straight ahead
+        assert_close(camera.get_ray(0.5f64, 0.5f64, (0.5, 0.5)).direction, Vector::unit(look_at - look_from));
+        assert_close(camera.w * -1f64, Vector::unit(look_at - look_from));
+        // The basis is orthonormal and u stays horizontal
+        assert!(Vector::dot(camera.u, camera.v).abs() < 1e-12);
+        assert!(Vector::dot(camera.v, camera.w).abs() < 1e-12);
+        assert!((camera.v.len() - 1f64).abs() < 1e-12);
+        assert!(camera.u.y.abs() < 1e-12);
+
+        // The top and bottom edges of the viewport are the field of view apart
+        let top = camera.get_ray(0.5f64, 1f64, (0.5, 0.5)).direction;
+        let bottom = camera.get_ray(0.5f64, 0f64, (0.5, 0.5)).direction;
+        let angle = f64::acos(Vector::dot(top, bottom)).to_degrees();
+        assert!((angle - 20f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_camera_zero_aperture_is_pinhole() {
+        let pinhole = Camera::default();
+        let lens = Camera::default().with_lens(0f64, 5f64);
+        for (s, t) in [(0f64, 0f64), (0.5f64, 0.5f64), (1f64, 0.25f64)] {
+            let (a, b) = (pinhole.get_ray(s, t, (0.5, 0.5)), lens.get_ray(s, t, (0.5, 0.5)));
+            assert_eq!(a.origin, b.origin);
+            assert_eq!(a.direction, b.direction);
+        }
+    }
+
+    #[test]
+    fn test_camera_lens_focus() {
+        let focus_distance = 4f64;
+        let camera = Camera::look_at(
+            Point::new(1f64, 2f64, 3f64),
+            Point::new(0f64, 0f64, -1f64),
+            Vector::new(0f64, 1f64, 0f64),
+            40f64,
+            1.5f64,
+        )
+        .with_lens(0.5f64, focus_distance);
+
+        for (s, t) in [(0.5f64, 0.5f64), (0.1f64, 0.9f64)] {
+            // Where the pinhole ray through (s, t) crosses the focus plane
+            let direction = camera.bottom_left_corner + camera.horizontal * s + camera.vertical * t - camera.origin;
+            let focus_point = camera.origin + direction * focus_distance;
+
+            for i in 0..100 {
+                let ray = camera.get_ray(s, t, (i as f64 / 100f64, (i * 37 % 100) as f64 / 100f64));
+                // Every ray starts on the lens disk and passes through the same point on the focus plane
+                let offset = ray.origin - camera.origin;
+                assert!(offset.len() <= 0.5f64);
+                assert!(Vector::dot(offset, camera.w).abs() < 1e-12);
+                let to_focus = focus_point - ray.origin;
+                assert!(Vector::cross(to_focus, ray.direction).len() < 1e-9);
+                assert!(Vector::dot(to_focus, ray.direction) > 0f64);
+            }
+        }
+    }
 }
@@ -0,0 +1,611 @@
+use crate::color::rgb::RgbColor;
+use crate::geometry::sphere::Sphere;
+use crate::geometry::vec3::Vector;
+use crate::geometry::vec3::Vector as Point;
+use crate::scene::background::Background;
+use crate::scene::camera::Camera;
+use crate::scene::intersect::Intersect;
+use crate::scene::light::Light;
+use crate::scene::material::dielectric::Dielectric;
+use crate::scene::material::light::DiffuseLight;
+use crate::scene::material::matte::Matte;
+use crate::scene::material::metal::Metal;
+use crate::scene::material::Material;
+use crate::settings::RenderSettings;
+use crate::world::World;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use toml::Spanned;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Declarative description of everything needed to render a scene, read from and written to TOML files
+///
+/// Colours are linear RGB, as used by the renderer, not the sRGB values of a colour picker.
+///
+/// ```toml
+/// [image]
+/// width = 400
+/// height = 225
+/// samples_per_pixel = 50
+/// max_depth = 50
+///
+/// [camera]
+/// look_from = [0.0, 0.0, 0.0]
+/// look_at = [0.0, 0.0, -1.0]
+/// vup = [0.0, 1.0, 0.0]
+/// vfov = 90.0
+/// aperture = 0.0
+///
+/// [background]
+/// type = "solid"
+/// color = [0.0, 0.0, 0.0]
+///
+/// [materials.lamp]
+/// type = "light"
+/// emit = [4.0, 4.0, 4.0]
+///
+/// [materials.ground]
+/// type = "matte"
+/// albedo = [0.2, 0.8, 0.8]
+///
+/// [[objects]]
+/// type = "sphere"
+/// center = [0.0, -100.5, -1.0]
+/// radius = 100.0
+/// material = "ground"
+///
+/// [[lights]]
+/// type = "point"
+/// position = [0.0, 2.0, -1.0]
+/// intensity = [5.0, 5.0, 5.0]
+/// ```
+///
+/// Materials are declared once by name and referenced by name from the objects. Objects made of a light material
+/// light the scene on their own, the lights are only needed for light sources of no size.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SceneDescription {
+    pub image: ImageDescription,
+    pub camera: CameraDescription,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<BackgroundDescription>,
+    pub materials: BTreeMap<String, MaterialDescription>,
+    pub objects: Vec<ObjectDescription>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lights: Vec<LightDescription>,
+}
+
+// The scene file as first read, with the materials and objects left as raw tables. Their `type` key decides how the
+// rest of the table is read, which loses track of where in the file each value came from, so they are converted one
+// by one afterwards to report errors against the table they are in.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSceneDescription {
+    image: ImageDescription,
+    camera: CameraDescription,
+    #[serde(default)]
+    background: Option<BackgroundDescription>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<toml::Table>>,
+    #[serde(default)]
+    objects: Vec<Spanned<toml::Table>>,
+    #[serde(default)]
+    lights: Vec<Spanned<toml::Table>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageDescription {
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: usize,
+    pub max_depth: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub look_from: [f64; 3],
+    pub look_at: [f64; 3],
+    #[serde(default = "CameraDescription::default_vup")]
+    pub vup: [f64; 3],
+    /// Vertical field of view in degrees
+    pub vfov: f64,
+    /// Radius of the lens, 0 for a pinhole camera with everything in focus
+    #[serde(default)]
+    pub aperture: f64,
+    /// Distance to the plane in focus, `look_at` is in focus when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus_distance: Option<f64>,
+}
+
+impl CameraDescription {
+    fn default_vup() -> [f64; 3] {
+        [0f64, 1f64, 0f64]
+    }
+}
+
+/// Light from outside the world, the default sky gradient when left out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum BackgroundDescription {
+    Gradient { bottom: [f64; 3], top: [f64; 3] },
+    Solid { color: [f64; 3] },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum MaterialDescription {
+    Matte { albedo: [f64; 3] },
+    Metal { albedo: [f64; 3], fuzz: f64 },
+    Dielectric { albedo: [f64; 3], refractive_index: f64 },
+    Light { emit: [f64; 3] },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ObjectDescription {
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+}
+
+/// Light source of no size, see `Light`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum LightDescription {
+    Point { position: [f64; 3], intensity: [f64; 3] },
+    Directional { direction: [f64; 3], irradiance: [f64; 3] },
+}
+
+/// A scene built from its description, ready to be rendered
+pub struct Scene {
+    pub world: World,
+    pub camera: Camera,
+    /// Settings from the `[image]` table, the rest are left to their defaults
+    pub settings: RenderSettings,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    /// The scene file could not be read or written
+    Io(std::io::Error),
+    /// The file is not valid TOML or does not match the scene layout. When the error is inside a material, object or
+    /// light the position is that of its table and `field` names it, e.g. `materials.glass` or `objects[2]`
+    Parse {
+        line: usize,
+        column: usize,
+        field: Option<String>,
+        message: String,
+    },
+    /// The file is well formed but a value in it is not usable
+    Invalid { field: String, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "{}", e),
+            SceneError::Parse {
+                line,
+                column,
+                field: None,
+                message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            SceneError::Parse {
+                line,
+                column,
+                field: Some(field),
+                message,
+            } => write!(f, "line {}, column {}: `{}`: {}", line, column, field, message),
+            SceneError::Invalid { field, message } => write!(f, "`{}`: {}", field, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(e: std::io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl SceneDescription {
+    /// Parses a scene description from TOML source
+    ///
+    /// # Arguments
+    /// * `source` - the TOML text
+    ///
+    /// # Returns
+    /// * `SceneError::Parse` with the line and column of the offending value if the text is not a scene description
+    /// * `SceneError::Invalid` with the name of the offending field if a value cannot be used
+    pub fn from_toml(source: &str) -> Result<Self, SceneError> {
+        let raw: RawSceneDescription =
+            toml::from_str(source).map_err(|e| parse_error(source, e.span(), None, e.message()))?;
+
+        // Read each table in turn, reporting errors at the table they are in
+        fn convert<T: for<'de> Deserialize<'de>>(
+            source: &str,
+            field: String,
+            table: Spanned<toml::Table>,
+        ) -> Result<T, SceneError> {
+            let span = table.span();
+            toml::Value::Table(table.into_inner())
+                .try_into()
+                .map_err(|e: toml::de::Error| parse_error(source, Some(span), Some(field), e.message().trim_end()))
+        }
+
+        let mut materials = BTreeMap::new();
+        for (name, table) in raw.materials {
+            let material = convert(source, format!("materials.{}", name), table)?;
+            materials.insert(name, material);
+        }
+        let mut objects = Vec::with_capacity(raw.objects.len());
+        for (i, table) in raw.objects.into_iter().enumerate() {
+            objects.push(convert(source, format!("objects[{}]", i), table)?);
+        }
+        let mut lights = Vec::with_capacity(raw.lights.len());
+        for (i, table) in raw.lights.into_iter().enumerate() {
+            lights.push(convert(source, format!("lights[{}]", i), table)?);
+        }
+
+        let description = SceneDescription {
+            image: raw.image,
+            camera: raw.camera,
+            background: raw.background,
+            materials,
+            objects,
+            lights,
+        };
+        description.validate()?;
+        Ok(description)
+    }
+
+    /// Reads and parses a scene description file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the description back out as TOML, in a form `from_toml` reads back unchanged
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("scene descriptions only hold TOML compatible values")
+    }
+
+    /// Writes the description to a scene file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), SceneError> {
+        let invalid = |field: String, message: &str| {
+            Err(SceneError::Invalid {
+                field,
+                message: message.to_string(),
+            })
+        };
+
+        if self.image.width == 0 || self.image.height == 0 {
+            return invalid("image".to_string(), "width and height must be at least 1");
+        }
+        if self.image.samples_per_pixel == 0 {
+            return invalid("image.samples_per_pixel".to_string(), "must be at least 1");
+        }
+        if !(self.camera.vfov > 0f64 && self.camera.vfov < 180f64) {
+            return invalid("camera.vfov".to_string(), "must be between 0 and 180 degrees");
+        }
+        let view = point(self.camera.look_at) - point(self.camera.look_from);
+        if view.len_sq() == 0f64 {
+            return invalid("camera.look_at".to_string(), "must be different from `look_from`");
+        }
+        if Vector::cross(view, point(self.camera.vup)).len_sq() == 0f64 {
+            return invalid("camera.vup".to_string(), "must not be parallel to the view direction");
+        }
+        if self.camera.aperture < 0f64 {
+            return invalid("camera.aperture".to_string(), "must not be negative");
+        }
+        if self.camera.focus_distance.is_some_and(|d| d <= 0f64) {
+            return invalid("camera.focus_distance".to_string(), "must be positive");
+        }
+
+        for (name, material) in &self.materials {
+            if let MaterialDescription::Dielectric {
+                refractive_index, ..
+            } = material
+            {
+                if *refractive_index <= 0f64 {
+                    return invalid(format!("materials.{}.refractive_index", name), "must be positive");
+                }
+            }
+        }
+
+        for (i, object) in self.objects.iter().enumerate() {
+            match object {
+                ObjectDescription::Sphere {
+                    radius, material, ..
+                } => {
+                    // Negative radii are allowed, they flip the normals to model hollow spheres
+                    if *radius == 0f64 {
+                        return invalid(format!("objects[{}].radius", i), "must not be zero");
+                    }
+                    if !self.materials.contains_key(material) {
+                        return invalid(
+                            format!("objects[{}].material", i),
+                            &format!("no material named `{}`", material),
+                        );
+                    }
+                }
+            }
+        }
+
+        for (i, light) in self.lights.iter().enumerate() {
+            if let LightDescription::Directional { direction, .. } = light {
+                if point(*direction).len_sq() == 0f64 {
+                    return invalid(format!("lights[{}].direction", i), "must not be zero");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds the world and camera described
+    pub fn build(&self) -> Scene {
+        let mut world = World::new();
+        match self.background {
+            Some(BackgroundDescription::Gradient { bottom, top }) => world.set_background(Background::Gradient {
+                bottom: color(bottom),
+                top: color(top),
+            }),
+            Some(BackgroundDescription::Solid { color: c }) => world.set_background(Background::Solid(color(c))),
+            None => {}
+        }
+        for object in &self.objects {
+            world.add(self.build_object(object));
+        }
+        for light in &self.lights {
+            world.add_light(match *light {
+                LightDescription::Point { position, intensity } => Light::Point {
+                    position: point(position),
+                    intensity: color(intensity),
+                },
+                LightDescription::Directional { direction, irradiance } => Light::Directional {
+                    direction: point(direction),
+                    irradiance: color(irradiance),
+                },
+            });
+        }
+
+        let camera = Camera::look_at(
+            point(self.camera.look_from),
+            point(self.camera.look_at),
+            point(self.camera.vup),
+            self.camera.vfov,
+            self.image.width as f64 / self.image.height as f64,
+        )
+        .with_lens(
+            self.camera.aperture,
+            self.camera
+                .focus_distance
+                .unwrap_or_else(|| (point(self.camera.look_at) - point(self.camera.look_from)).len()),
+        );
+
+        Scene {
+            world,
+            camera,
+            settings: RenderSettings {
+                samples_per_pixel: self.image.samples_per_pixel,
+                max_depth: self.image.max_depth,
+                seed: self.image.seed,
+                ..RenderSettings::new(self.image.width, self.image.height)
+            },
+        }
+    }
+
+    fn build_object(&self, object: &ObjectDescription) -> Box<dyn Intersect> {
+        match object {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => Box::new(Sphere::new(point(*center), *radius, self.build_material(material))),
+        }
+    }
+
+    fn build_material(&self, name: &str) -> Box<dyn Material> {
+        // Every material reference was checked when the description was parsed
+        match &self.materials[name] {
+            MaterialDescription::Matte { albedo } => Box::new(Matte::new(color(*albedo))),
+            MaterialDescription::Metal { albedo, fuzz } => Box::new(Metal::new(color(*albedo), *fuzz)),
+            MaterialDescription::Dielectric {
+                albedo,
+                refractive_index,
+            } => Box::new(Dielectric::new(color(*albedo), *refractive_index)),
+            MaterialDescription::Light { emit } => Box::new(DiffuseLight::new(color(*emit))),
+        }
+    }
+}
+
+// Spans are byte offsets into the source, turn them into 1-based line and column numbers
+fn parse_error(source: &str, span: Option<Range<usize>>, field: Option<String>, message: &str) -> SceneError {
+    let offset = span.map_or(0, |span| span.start);
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    SceneError::Parse {
+        line,
+        column,
+        field,
+        message: message.to_string(),
+    }
+}
+
+fn point(p: [f64; 3]) -> Point {
+    Point::new(p[0], p[1], p[2])
+}
+
+fn color(c: [f64; 3]) -> RgbColor {
+    RgbColor::new(c[0], c[1], c[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = r#"
+[image]
+width = 40
+height = 20
+samples_per_pixel = 4
+max_depth = 8
+seed = 42
+
+[camera]
+look_from = [0.0, 0.0, 0.0]
+look_at = [0.0, 0.0, -1.0]
+vfov = 90.0
+aperture = 0.05
+focus_distance = 1.5
+
+[background]
+type = "solid"
+color = [0.1, 0.1, 0.1]
+
+[materials.lamp]
+type = "light"
+emit = [4.0, 4.0, 4.0]
+
+[materials.ground]
+type = "matte"
+albedo = [0.2, 0.8, 0.8]
+
+[materials.glass]
+type = "dielectric"
+albedo = [1.0, 1.0, 1.0]
+refractive_index = 1.5
+
+[[objects]]
+type = "sphere"
+center = [0.0, -100.5, -1.0]
+radius = 100.0
+material = "ground"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, -1.0]
+radius = 0.5
+material = "glass"
+
+[[lights]]
+type = "directional"
+direction = [0.0, -1.0, 0.5]
+irradiance = [2.0, 2.0, 2.0]
+"#;
+
+    #[test]
+    fn test_scene_round_trip() {
+        let description = SceneDescription::from_toml(SCENE).unwrap();
+        assert_eq!(description.objects.len(), 2);
+        assert_eq!(
+            description.lights,
+            vec![LightDescription::Directional {
+                direction: [0.0, -1.0, 0.5],
+                irradiance: [2.0, 2.0, 2.0]
+            }]
+        );
+        assert_eq!(
+            description.materials["glass"],
+            MaterialDescription::Dielectric {
+                albedo: [1.0, 1.0, 1.0],
+                refractive_index: 1.5
+            }
+        );
+        let written = description.to_toml();
+        assert_eq!(SceneDescription::from_toml(&written).unwrap(), description);
+    }
+
+    #[test]
+    fn test_scene_build() {
+        let scene = SceneDescription::from_toml(SCENE).unwrap().build();
+        assert_eq!(scene.camera.aspect_ratio, 2f64);
+        assert_eq!(scene.settings.width, 40);
+        assert_eq!(scene.settings.samples_per_pixel, 4);
+        assert_eq!(scene.world.size(), 2);
+    }
+
+    #[test]
+    fn test_scene_parse_error_location() {
+        // Errors in the fixed sections point at the offending value
+        let source = SCENE.replace("samples_per_pixel = 4", "samples_per_pixel = -4");
+        match SceneDescription::from_toml(&source) {
+            Err(SceneError::Parse {
+                line, column, field, ..
+            }) => assert_eq!((line, column, field), (5, 21, None)),
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+
+        // Errors in materials and objects point at their table
+        let source = SCENE.replace("radius = 0.5", "radius = \"big\"");
+        match SceneDescription::from_toml(&source) {
+            Err(SceneError::Parse {
+                line, column, field, ..
+            }) => assert_eq!((line, column, field), (39, 1, Some("objects[1]".to_string()))),
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+
+        let source = SCENE.replace("type = \"dielectric\"", "type = \"marble\"");
+        match SceneDescription::from_toml(&source) {
+            Err(SceneError::Parse { line, field, .. }) => {
+                assert_eq!((line, field), (28, Some("materials.glass".to_string())))
+            }
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_scene_camera_validation() {
+        let source = SCENE.replace("look_at = [0.0, 0.0, -1.0]", "look_at = [0.0, 0.0, 0.0]");
+        match SceneDescription::from_toml(&source) {
+            Err(SceneError::Invalid { field, .. }) => assert_eq!(field, "camera.look_at"),
+            other => panic!("expected an invalid field error, got {:?}", other.map(|_| ())),
+        }
+        let source = SCENE.replace("vfov = 90.0", "vfov = 90.0\nvup = [0.0, 0.0, 2.0]");
+        match SceneDescription::from_toml(&source) {
+            Err(SceneError::Invalid { field, .. }) => assert_eq!(field, "camera.vup"),
+            other => panic!("expected an invalid field error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_scene_lights() {
+        let source = SCENE.replace("irradiance = [2.0, 2.0, 2.0]", "intensity = [2.0, 2.0, 2.0]");
+        match SceneDescription::from_toml(&source) {
+            Err(SceneError::Parse { line, field, .. }) => assert_eq!((line, field), (45, Some("lights[0]".to_string()))),
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+        let source = SCENE.replace("direction = [0.0, -1.0, 0.5]", "direction = [0.0, 0.0, 0.0]");
+        match SceneDescription::from_toml(&source) {
+            Err(SceneError::Invalid { field, .. }) => assert_eq!(field, "lights[0].direction"),
+            other => panic!("expected an invalid field error, got {:?}", other.map(|_| ())),
+        }
+        // Scenes without lights are written without the table
+        let mut description = SceneDescription::from_toml(SCENE).unwrap();
+        description.lights.clear();
+        assert!(!description.to_toml().contains("lights"));
+    }
+
+    #[test]
+    fn test_scene_unknown_material() {
+        let source = SCENE.replace("material = \"glass\"", "material = \"gold\"");
+        match SceneDescription::from_toml(&source) {
+            Err(SceneError::Invalid { field, .. }) => assert_eq!(field, "objects[1].material"),
+            other => panic!("expected an invalid field error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::color::antialias::ReconstructionFilter;
+use crate::color::rgb::RgbColor;
+use std::ops::Range;
+
+// Smallest sum of sample weights a pixel is reconstructed from, a single sample at the centre weighs 1
+const MIN_WEIGHT: f64 = 1e-3;
+
+/// Sensor of the camera, where the samples of a render are splatted and weighed into pixels
+///
+/// Film positions are in pixels from the bottom left corner of the image, x to the right and y up, so pixel (x, y)
+/// covers [x, x + 1) x [y, y + 1) and its centre is at (x + 0.5, y + 0.5). Every sample is added to all the pixels
+/// within the radius of the reconstruction filter, weighed by the filter. A pixel ends up as the weighted average of
+/// the samples around it.
+///
+/// A film can hold only a band of rows of the image, so that render threads can each fill their own and have them
+/// merged into the full image.
+///
+/// # Examples
+/// ```
+/// # use rusttracing::color::antialias::ReconstructionFilter;
+/// # use rusttracing::color::rgb::RgbColor;
+/// # use rusttracing::scene::film::Film;
+/// let mut film = Film::new(2, 1, ReconstructionFilter::Box(0.5));
+/// film.add_sample((0.25, 0.5), RgbColor::new(1.0, 1.0, 1.0));
+/// film.add_sample((0.75, 0.5), RgbColor::new(0.0, 0.0, 0.0));
+/// film.add_sample((1.5, 0.5), RgbColor::new(0.5, 0.0, 0.0));
+/// let image = film.into_image();
+/// assert_eq!(image, vec![RgbColor::new(0.5, 0.5, 0.5), RgbColor::new(0.5, 0.0, 0.0)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Film {
+    width: u32,
+    filter: ReconstructionFilter,
+    // Rows held by this film, bottom up
+    rows: Range<u32>,
+    // Weighted sum of the samples and sum of their weights for every pixel held, bottom row first
+    pixels: Vec<(RgbColor, f64)>,
+}
+
+impl Film {
+    /// Empty film for a whole image of the given size
+    pub fn new(width: u32, height: u32, filter: ReconstructionFilter) -> Self {
+        Film::with_rows(width, filter, 0..height)
+    }
+
+    fn with_rows(width: u32, filter: ReconstructionFilter, rows: Range<u32>) -> Self {
+        Film {
+            width,
+            filter,
+            pixels: vec![(RgbColor::default(), 0f64); width as usize * rows.len()],
+            rows,
+        }
+    }
+
+    /// Empty film for the rows of an image of the given size that samples taken in `row` count towards
+    pub fn tile(width: u32, height: u32, filter: ReconstructionFilter, row: u32) -> Self {
+        // Samples in the row are in [row, row + 1) and reach the pixel centres less than a radius away
+        let reach = filter.radius().ceil() as u32;
+        let rows = row.saturating_sub(reach)..(row + reach + 1).min(height);
+        Film::with_rows(width, filter, rows)
+    }
+
+    /// Splats a sample at film `position` onto the pixels around it
+    pub fn add_sample(&mut self, position: (f64, f64), color: RgbColor) {
+        // 1. Pixels whose centre is within the filter radius, clipped to the rows held
+        let radius = self.filter.radius();
+        let span = |p: f64, range: Range<u32>| {
+            let first = (p - 0.5 - radius).ceil().max(range.start as f64) as u32;
+            let last = (p - 0.5 + radius).floor().min(range.end as f64 - 1f64);
+            (first, last)
+        };
+        let (x0, x1) = span(position.0, 0..self.width);
+        let (y0, y1) = span(position.1, self.rows.clone());
+        if x1 < x0 as f64 || y1 < y0 as f64 {
+            return;
+        }
+
+        // 2. Weigh the sample by its offset from each pixel centre
+        for y in y0..=y1 as u32 {
+            let dy = position.1 - (y as f64 + 0.5);
+            let row = (y - self.rows.start) as usize * self.width as usize;
+            for x in x0..=x1 as u32 {
+                let weight = self.filter.weight(position.0 - (x as f64 + 0.5), dy);
+                if weight != 0f64 {
+                    let pixel = &mut self.pixels[row + x as usize];
+                    pixel.0 = pixel.0 + color * weight;
+                    pixel.1 += weight;
+                }
+            }
+        }
+    }
+
+    /// Adds the samples of a tile of the same image to this film
+    pub fn merge(&mut self, tile: &Film) {
+        let width = self.width as usize;
+        for y in tile.rows.start.max(self.rows.start)..tile.rows.end.min(self.rows.end) {
+            let own = (y - self.rows.start) as usize * width;
+            let other = (y - tile.rows.start) as usize * width;
+            for x in 0..width {
+                let (color, weight) = tile.pixels[other + x];
+                let pixel = &mut self.pixels[own + x];
+                pixel.0 = pixel.0 + color;
+                pixel.1 += weight;
+            }
+        }
+    }
+
+    /// The reconstructed pixels, row by row from the top left like the image files. Pixels without enough sample
+    /// weight are black.
+    pub fn into_image(self) -> Vec<RgbColor> {
+        let width = self.width as usize;
+        self.pixels
+            .chunks_exact(width.max(1))
+            .rev()
+            .flat_map(|row| {
+                row.iter().map(|&(color, weight)| {
+                    // With negative lobes and few samples the weights can cancel out or add up below zero, dividing by
+                    // them would blow the pixel up or flip its sign
+                    if weight > MIN_WEIGHT {
+                        let color = color * (1f64 / weight);
+                        RgbColor::new(color.x.max(0f64), color.y.max(0f64), color.z.max(0f64))
+                    } else {
+                        RgbColor::default()
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_box_filter_averages_pixel_samples() {
+        let mut film = Film::new(3, 2, ReconstructionFilter::Box(0.5));
+        film.add_sample((0.1, 0.1), RgbColor::new(1f64, 0f64, 0f64));
+        film.add_sample((0.9, 0.9), RgbColor::new(0f64, 1f64, 0f64));
+        film.add_sample((2.5, 1.99), RgbColor::new(0f64, 0f64, 4f64));
+        let image = film.into_image();
+        // Top row first
+        assert_eq!(image[2], RgbColor::new(0f64, 0f64, 4f64));
+        assert_eq!(image[3], RgbColor::new(0.5, 0.5, 0f64));
+        assert_eq!(image[1], RgbColor::default());
+    }
+
+    #[test]
+    fn test_wide_filters_spread_samples() {
+        let mut film = Film::new(5, 5, ReconstructionFilter::Tent(1.5));
+        film.add_sample((2.5, 2.5), RgbColor::new(1f64, 1f64, 1f64));
+        let image = film.into_image();
+        // The sample reaches the direct neighbours but not the pixels two away
+        assert_eq!(image[2 * 5 + 2], RgbColor::new(1f64, 1f64, 1f64));
+        assert_eq!(image[2 * 5 + 1], RgbColor::new(1f64, 1f64, 1f64));
+        assert_eq!(image[5 + 1], RgbColor::new(1f64, 1f64, 1f64));
+        assert_eq!(image[2 * 5], RgbColor::default());
+        assert_eq!(image[2], RgbColor::default());
+    }
+
+    #[test]
+    fn test_negative_lobes_stay_bounded() {
+        // A single sample between two pixel centres only shows in the pixels it has a positive weight for
+        let white = RgbColor::new(1f64, 1f64, 1f64);
+        let mut film = Film::new(4, 1, ReconstructionFilter::Lanczos(2f64));
+        film.add_sample((1f64, 0.5), white);
+        assert_eq!(film.into_image(), vec![white, white, RgbColor::default(), RgbColor::default()]);
+        // A bright sample in the negative lobe of a dim pixel cannot make it negative
+        let mut film = Film::new(4, 1, ReconstructionFilter::Lanczos(2f64));
+        film.add_sample((1f64, 0.5), white * 10f64);
+        film.add_sample((2.5, 0.5), white * 0.1);
+        assert_eq!(film.into_image()[2], RgbColor::default());
+    }
+
+    #[test]
+    fn test_merged_tiles_match_single_film() {
+        let filter = ReconstructionFilter::Mitchell(2f64);
+        let (width, height) = (7, 6);
+        let mut whole = Film::new(width, height, filter);
+        let mut merged = Film::new(width, height, filter);
+        for row in 0..height {
+            let mut tile = Film::tile(width, height, filter, row);
+            for i in 0..20 {
+                let position = ((i % 7) as f64 + 0.37 * (i % 3) as f64, row as f64 + 0.05 * i as f64);
+                let color = RgbColor::new(i as f64, row as f64, 1f64);
+                whole.add_sample(position, color);
+                tile.add_sample(position, color);
+            }
+            merged.merge(&tile);
+        }
+        for (a, b) in whole.into_image().iter().zip(merged.into_image()) {
+            assert!((*a - b).len() < 1e-12);
+        }
+    }
+}
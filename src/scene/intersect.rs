@@ -3,6 +3,7 @@ use crate::geometry::vec3::Vector;
 use crate::scene::ray::Ray;
 use crate::scene::material::Material;
 use crate::color::rgb::RgbColor;
+use crate::geometry::aabb::Aabb;
 
 pub struct Intersection {
     // Intersections happen when a ray crosses the bounds of an object that implements this trait
@@ -21,21 +22,79 @@ pub struct Intersection {
     // where O is the origin of the ray, D is the direction
     // Think of this as the distance travelled by the ray from its origin
     pub t: f64,
+
+    // Whether the ray hit the outside of the surface. The normal always points against the ray, so this is
+    // the only record of which side was hit, e.g. whether a ray is entering or leaving a refractive material
+    pub front_face: bool,
+
+    // Texture coordinates of the hit point on the surface, (0, 0) for objects without any
+    pub uv: (f64, f64),
+
+    // Weights of the three vertices of a triangle at the hit point, they sum to 1 and give the point as
+    // w0 * v0 + w1 * v1 + w2 * v2. Only triangles have them, any other object leaves this as None
+    pub barycentric: Option<(f64, f64, f64)>,
+
+    // Colour of the surface at the hit point for objects coloured per vertex, None for any other object
+    pub color: Option<RgbColor>,
 }
 
 impl Intersection {
-    pub fn new(point: Point, normal: Vector, t: f64) -> Self {
+    pub fn new(point: Point, normal: Vector, t: f64, front_face: bool) -> Self {
         Intersection {
             point,
             normal,
             t,
+            front_face,
+            uv: (0f64, 0f64),
+            barycentric: None,
+            color: None,
+        }
+    }
+
+    pub fn with_uv(self, uv: (f64, f64)) -> Self {
+        Intersection { uv, ..self }
+    }
+
+    pub fn with_barycentric(self, barycentric: (f64, f64, f64)) -> Self {
+        Intersection {
+            barycentric: Some(barycentric),
+            ..self
         }
     }
+
+    pub fn with_color(self, color: RgbColor) -> Self {
+        Intersection {
+            color: Some(color),
+            ..self
+        }
+    }
+}
+
+/// Point picked on the surface of an object, for lighting a point of the scene with it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SurfaceSample {
+    pub point: Point,
+    // Unit normal of the surface at the point, on the outside for closed objects
+    pub normal: Vector,
+    // Density of the direction from the point lit towards `point`, per unit solid angle
+    pub pdf: f64,
 }
 
-pub trait Intersect {
+// The world is shared by reference between the render threads
+pub trait Intersect: Send + Sync {
     // type HitList = std::vec::Vec::<crate::scene::intersect::Intersections as Trait>::new();
 
-    fn intersects(self: &Self, ray: &Ray, range: (f64, f64)) -> Option<Intersection>;
-    fn material(&self) -> &dyn Material<Albedo=RgbColor>;
-}
\ No newline at end of file
+    fn intersects(&self, ray: &Ray, range: (f64, f64)) -> Option<Intersection>;
+    // Whether the ray hits the object at all within the range, for shadow rays that do not need the closest hit
+    fn occludes(&self, ray: &Ray, range: (f64, f64)) -> bool {
+        self.intersects(ray, range).is_some()
+    }
+    fn material(&self) -> &dyn Material;
+    // Box enclosing the whole object, used to skip the object when a ray misses it
+    fn bounding_box(&self) -> Aabb;
+    // Picks a point of the surface seen from `origin` from a 2D sample, for objects made of emissive materials that
+    // light the scene. None when there is no point to pick, e.g. from the plane of a triangle.
+    fn sample_surface(&self, origin: Point, u: (f64, f64)) -> Option<SurfaceSample>;
+    // Density `sample_surface` picks the point of `hit` with from `origin`, per unit solid angle
+    fn surface_pdf(&self, origin: Point, hit: &Intersection) -> f64;
+}
@@ -0,0 +1,90 @@
+use crate::color::rgb::RgbColor;
+use crate::geometry::vec3::Vector;
+use crate::geometry::vec3::Vector as Point;
+
+/// Light source of no size, which rays never hit and which can only be reached by sampling it
+///
+/// Emissive objects light the scene too, they are picked up by the world from their material.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Light {
+    /// Light given off by a single point, the same in every direction. `intensity` is the power per unit solid angle,
+    /// the light reaching a surface facing the point falls off with the square of its distance.
+    Point { position: Point, intensity: RgbColor },
+    /// Light arriving from infinitely far away along `direction`, like sunlight. `irradiance` is the light reaching a
+    /// surface facing it, wherever the surface is.
+    Directional { direction: Vector, irradiance: RgbColor },
+}
+
+/// Light reaching a point of the scene from a light source, as long as nothing is in the way
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightSample {
+    // Unit direction from the point lit towards the light
+    pub wi: Vector,
+    // Distance to the light along `wi`, infinite for lights outside the world
+    pub distance: f64,
+    // Light arriving along `wi`
+    pub radiance: RgbColor,
+    // Density `wi` was picked with, per unit solid angle. For lights with a single direction this is the
+    // probability of picking the light and `radiance` holds the light it brings, as for specular BSDF samples.
+    pub pdf: f64,
+    // Whether `wi` is the only direction the light comes from, so that no ray can find the light by chance
+    pub delta: bool,
+}
+
+impl Light {
+    /// Light arriving at `point`
+    pub fn sample(&self, point: Point) -> LightSample {
+        match *self {
+            Light::Point { position, intensity } => {
+                let to_light = position - point;
+                let distance_sq = to_light.len_sq();
+                LightSample {
+                    wi: to_light / distance_sq.sqrt(),
+                    distance: distance_sq.sqrt(),
+                    radiance: intensity / distance_sq,
+                    pdf: 1f64,
+                    delta: true,
+                }
+            }
+            Light::Directional { direction, irradiance } => LightSample {
+                wi: Vector::unit(direction) * -1f64,
+                distance: f64::INFINITY,
+                radiance: irradiance,
+                pdf: 1f64,
+                delta: true,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point_light_falls_off_with_distance() {
+        let light = Light::Point {
+            position: Point::new(0f64, 4f64, 0f64),
+            intensity: RgbColor::new(8f64, 16f64, 32f64),
+        };
+        let sample = light.sample(Point::new(0f64, 2f64, 0f64));
+        assert_eq!(sample.wi, Vector::new(0f64, 1f64, 0f64));
+        assert_eq!(sample.distance, 2f64);
+        assert_eq!(sample.radiance, RgbColor::new(2f64, 4f64, 8f64));
+        assert!(sample.delta);
+    }
+
+    #[test]
+    fn test_directional_light_is_the_same_everywhere() {
+        let light = Light::Directional {
+            direction: Vector::new(0f64, -2f64, 0f64),
+            irradiance: RgbColor::new(1f64, 1f64, 1f64),
+        };
+        for point in [Point::default(), Point::new(100f64, -3f64, 7f64)] {
+            let sample = light.sample(point);
+            assert_eq!(sample.wi, Vector::new(0f64, 1f64, 0f64));
+            assert_eq!(sample.distance, f64::INFINITY);
+            assert_eq!(sample.radiance, RgbColor::new(1f64, 1f64, 1f64));
+        }
+    }
+}
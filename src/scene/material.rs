@@ -1,30 +1,69 @@
 use crate::color::rgb::RgbColor;
 use crate::geometry::vec3::Vector;
 use crate::scene::intersect::Intersection;
-use crate::scene::ray::Ray;
-use rand::Rng;
+use crate::scene::sampler::Sampler;
 
+pub mod dielectric;
+pub mod light;
 pub mod matte;
 pub mod metal;
 
-pub trait Material {
-    type Albedo;
-    fn scatter(&self, hit_ray: &Ray, hit: &Intersection) -> Option<Ray>;
-    fn albedo(&self) -> &Self::Albedo;
+/// Direction drawn from the BSDF of a material, with what the integrator needs to weigh the light it brings back
+///
+/// The estimate of the light leaving along `wo` is the light arriving along `wi` times `f * |cos θi| / pdf`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BsdfSample {
+    // Direction the light arrives from, in the local shading frame
+    pub wi: Vector,
+    // Value of the BSDF for the pair of directions
+    pub f: RgbColor,
+    // Density `wi` was drawn with, per unit solid angle. For a specular direction this is the probability of picking
+    // it and `f` holds the matching weight, as if the delta distribution had been integrated out
+    pub pdf: f64,
+    // Whether `wi` is the only direction the light could come from, which `eval` and `pdf` then never return
+    pub specular: bool,
+}
+
+// Materials are shared between the render threads, and every random number they need is drawn from the
+// sampler of the thread tracing the ray. A sample may use up to two dimensions, and warps them with
+// `geometry::sampling`.
+//
+// Directions are unit vectors in the local shading frame of the hit, `Frame::from_normal(hit.normal)`, pointing away
+// from the surface: `wo` towards where the light goes, back along the ray that hit, and `wi` towards where it comes
+// from. The normal always faces the ray, so `wo.z` is positive and the cosine of a direction with the normal is its
+// z component.
+pub trait Material: Send + Sync {
+    /// Draws the direction of the light scattered towards `wo`, None when the material absorbs it
+    fn sample(&self, wo: Vector, hit: &Intersection, sampler: &mut dyn Sampler) -> Option<BsdfSample>;
+
+    /// Fraction of the light arriving along `wi` that is scattered towards `wo`, per unit solid angle. Always black
+    /// for specular materials, whose directions only `sample` can find.
+    fn eval(&self, wo: Vector, wi: Vector, hit: &Intersection) -> RgbColor;
+
+    /// Density `sample` draws `wi` with for `wo`, per unit solid angle
+    fn pdf(&self, wo: Vector, wi: Vector, hit: &Intersection) -> f64;
+
+    /// Colour the material scatters light with
+    fn albedo(&self) -> RgbColor;
+
+    // Albedo at the hit point, the same everywhere unless the material takes its colour from the surface
+    fn albedo_at(&self, _hit: &Intersection) -> RgbColor {
+        self.albedo()
+    }
+
+    // Whether the material only scatters light into single directions, which `sample` is the only way to find. No
+    // light is sampled at such surfaces.
+    fn is_specular(&self) -> bool {
+        false
+    }
 
-    fn random_in_unit_sphere(&self) -> Vector {
-        let x: f64 = rand::thread_rng().gen_range(0f64..1f64);
-        let y: f64 = rand::thread_rng().gen_range(0f64..1f64);
-        let z: f64 = rand::thread_rng().gen_range(0f64..1f64);
-        Vector { x, y, z }
+    // Light given off by the surface at the hit point, most materials do not glow
+    fn emitted(&self, _hit: &Intersection) -> RgbColor {
+        RgbColor::default()
     }
 
-    fn random_in_hemisphere(&self, normal: Vector) -> Vector {
-        let random_vector = self.random_in_unit_sphere();
-        if Vector::dot(random_vector, normal) > 0f64 {
-            random_vector
-        } else {
-            random_vector * -1f64
-        }
+    // Whether `emitted` is ever anything but black, which makes the objects made of the material lights of the world
+    fn is_emissive(&self) -> bool {
+        false
     }
 }
@@ -0,0 +1,171 @@
+use crate::scene::material::{BsdfSample, Material};
+use crate::color::rgb::RgbColor;
+use crate::geometry::vec3::Vector;
+use crate::scene::intersect::Intersection;
+use crate::scene::sampler::Sampler;
+
+pub struct Dielectric {
+    albedo: RgbColor,
+    refractive_index: f64,
+}
+
+impl Dielectric {
+    pub fn new(albedo: RgbColor, refractive_index: f64) -> Self {
+        Dielectric {
+            albedo,
+            refractive_index,
+        }
+    }
+
+    /// Snell's law
+    /// η⋅sinθ = η′⋅sinθ′
+    /// The refracted ray R′ is split into a part perpendicular to the normal and a part parallel to it
+    /// R′⊥ = η/η′ * (R + cosθ*N)
+    /// R′∥ = -sqrt(1 - |R′⊥|²) * N
+    fn refract(incident: &Vector, normal: &Vector, eta_ratio: f64) -> Vector {
+        let cos_theta = f64::min(Vector::dot(*incident * -1f64, *normal), 1f64);
+        let r_out_perp = (*incident + *normal * cos_theta) * eta_ratio;
+        let r_out_parallel = *normal * -f64::sqrt(f64::abs(1f64 - r_out_perp.len_sq()));
+        r_out_perp + r_out_parallel
+    }
+
+    fn reflect(incident: &Vector, normal: &Vector) -> Vector {
+        *incident - *normal * Vector::dot(*incident, *normal) * 2f64
+    }
+
+    /// Schlick's approximation for the Fresnel reflectance
+    /// R(θ) = R0 + (1 - R0)(1 - cosθ)^5
+    /// R0 = ((1 - η) / (1 + η))²
+    fn reflectance(cosine: f64, eta_ratio: f64) -> f64 {
+        let r0 = (1f64 - eta_ratio) / (1f64 + eta_ratio);
+        let r0 = r0 * r0;
+        r0 + (1f64 - r0) * (1f64 - cosine).powi(5)
+    }
+
+    // Picks between the reflected and the refracted direction with the probability of the light taking each,
+    // in the local shading frame
+    fn refract_or_reflect(&self, wo: Vector, front_face: bool, sampler: &mut dyn Sampler) -> Option<BsdfSample> {
+        // Rays entering the surface go from air into the material, rays leaving go the other way around
+        let eta_ratio = if front_face {
+            1f64 / self.refractive_index
+        } else {
+            self.refractive_index
+        };
+
+        let normal = Vector::new(0f64, 0f64, 1f64);
+        let incident = wo * -1f64;
+        let cos_theta = f64::min(wo.z, 1f64);
+        let sin_theta = f64::sqrt(1f64 - cos_theta * cos_theta);
+
+        // Total internal reflection: Snell's law has no solution when η/η′ * sinθ > 1
+        let cannot_refract = eta_ratio * sin_theta > 1f64;
+        let reflectance = if cannot_refract {
+            1f64
+        } else {
+            Self::reflectance(cos_theta, eta_ratio)
+        };
+        let (wi, pdf) = if reflectance > sampler.get_1d() {
+            (Self::reflect(&incident, &normal), reflectance)
+        } else {
+            (Self::refract(&incident, &normal, eta_ratio), 1f64 - reflectance)
+        };
+        if wi.z == 0f64 {
+            return None;
+        }
+        // Weighed by the albedo once the cosine and the probability of the direction are divided out
+        Some(BsdfSample {
+            wi,
+            f: self.albedo * (pdf / wi.z.abs()),
+            pdf,
+            specular: true,
+        })
+    }
+}
+
+// Smooth glass, water and the like, which reflect and refract the light in a single direction each
+impl Material for Dielectric {
+    fn sample(&self, wo: Vector, hit: &Intersection, sampler: &mut dyn Sampler) -> Option<BsdfSample> {
+        self.refract_or_reflect(wo, hit.front_face, sampler)
+    }
+
+    fn eval(&self, _wo: Vector, _wi: Vector, _hit: &Intersection) -> RgbColor {
+        RgbColor::default()
+    }
+
+    fn pdf(&self, _wo: Vector, _wi: Vector, _hit: &Intersection) -> f64 {
+        0f64
+    }
+
+    fn albedo(&self) -> RgbColor {
+        self.albedo
+    }
+
+    fn is_specular(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::sampler::IndependentSampler;
+
+    #[test]
+    fn test_refract_straight_through() {
+        // A ray hitting the surface head on is not bent, whatever the refractive index
+        let incident = Vector::new(0f64, 0f64, -1f64);
+        let normal = Vector::new(0f64, 0f64, 1f64);
+        assert_eq!(incident, Dielectric::refract(&incident, &normal, 1f64 / 1.5f64));
+    }
+
+    #[test]
+    fn test_refract_snells_law() {
+        // 45 degrees from air into glass, sinθ′ = sinθ / 1.5
+        let incident = Vector::unit(Vector::new(1f64, 0f64, -1f64));
+        let normal = Vector::new(0f64, 0f64, 1f64);
+        let refracted = Dielectric::refract(&incident, &normal, 1f64 / 1.5f64);
+        let sin_theta = f64::sqrt(0.5f64);
+        assert!((refracted.x - sin_theta / 1.5f64).abs() < 1e-12);
+        assert!((refracted.len() - 1f64).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_total_internal_reflection() {
+        // Leaving glass at a grazing angle always reflects
+        let glass = Dielectric::new(RgbColor::new(1f64, 1f64, 1f64), 1.5f64);
+        let wo = Vector::unit(Vector::new(-1f64, 0f64, 0.2f64));
+        let sample = glass.refract_or_reflect(wo, false, &mut IndependentSampler::new(0)).unwrap();
+        assert!(sample.wi.z > 0f64);
+        assert_eq!(sample.pdf, 1f64);
+    }
+
+    #[test]
+    fn test_samples_are_weighed_by_albedo() {
+        // Whether the light is reflected or refracted, the probability of the direction cancels out its share
+        let albedo = RgbColor::new(0.9f64, 1f64, 0.9f64);
+        let glass = Dielectric::new(albedo, 1.5f64);
+        let hit = Intersection::new(Vector::default(), Vector::new(0f64, 0f64, 1f64), 1f64, true);
+        let wo = Vector::unit(Vector::new(1f64, 0f64, 1f64));
+        let mut sampler = IndependentSampler::new(2);
+        let (mut reflected, mut refracted) = (0, 0);
+        for _ in 0..1000 {
+            let sample = glass.sample(wo, &hit, &mut sampler).unwrap();
+            assert!(sample.specular);
+            assert!((sample.f * (sample.wi.z.abs() / sample.pdf) - albedo).len() < 1e-12);
+            if sample.wi.z > 0f64 {
+                reflected += 1;
+            } else {
+                refracted += 1;
+            }
+        }
+        assert!(reflected > 0 && refracted > reflected);
+        assert_eq!(glass.eval(wo, Vector::new(-wo.x, -wo.y, wo.z), &hit), RgbColor::default());
+    }
+
+    #[test]
+    fn test_reflectance() {
+        // Normal incidence on glass reflects about 4% of the light, grazing incidence all of it
+        assert!((Dielectric::reflectance(1f64, 1f64 / 1.5f64) - 0.04f64).abs() < 1e-12);
+        assert!((Dielectric::reflectance(0f64, 1f64 / 1.5f64) - 1f64).abs() < 1e-12);
+    }
+}
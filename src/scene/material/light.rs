@@ -0,0 +1,42 @@
+use crate::scene::material::{BsdfSample, Material};
+use crate::color::rgb::RgbColor;
+use crate::geometry::vec3::Vector;
+use crate::scene::intersect::Intersection;
+use crate::scene::sampler::Sampler;
+
+/// Surface that gives off light evenly in every direction and reflects none
+pub struct DiffuseLight {
+    emit: RgbColor,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: RgbColor) -> Self {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn sample(&self, _wo: Vector, _hit: &Intersection, _sampler: &mut dyn Sampler) -> Option<BsdfSample> {
+        None
+    }
+
+    fn eval(&self, _wo: Vector, _wi: Vector, _hit: &Intersection) -> RgbColor {
+        RgbColor::default()
+    }
+
+    fn pdf(&self, _wo: Vector, _wi: Vector, _hit: &Intersection) -> f64 {
+        0f64
+    }
+
+    fn albedo(&self) -> RgbColor {
+        RgbColor::default()
+    }
+
+    fn emitted(&self, _hit: &Intersection) -> RgbColor {
+        self.emit
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.emit != RgbColor::default()
+    }
+}
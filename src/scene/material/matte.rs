@@ -1,43 +1,119 @@
-use crate::scene::material::Material;
+use crate::scene::material::{BsdfSample, Material};
 use crate::color::rgb::RgbColor;
+use crate::geometry::sampling;
 use crate::geometry::vec3::Vector;
-use crate::geometry::vec3::Vector as Point;
-use crate::scene::ray::Ray;
 use crate::scene::intersect::Intersection;
+use crate::scene::sampler::Sampler;
+use std::f64::consts::FRAC_1_PI;
 
 pub struct Matte {
     albedo: RgbColor,
+    // Use the colour of the surface at the hit point where it has one, e.g. meshes coloured per vertex
+    vertex_colors: bool,
 }
 
 impl Matte {
     pub fn new(albedo: RgbColor) -> Self {
-        Matte { albedo }
-    }
-
-    /// Lamberts cosine law
-    /// RgbColor ∝ dot(I,N)
-    /// I = I0*Kd*dot(I,N)/(|I|*|N|)
-    /// N is the normal of the surface
-    /// I is the incident vector
-    /// As the angle increases, the light gets weaker
-    fn lambertian_diffuse(&self, incident: &Vector, normal: &Vector, hit: &Point) -> Option<Ray> {
-        // let dir = self.random_in_unit_sphere() + *normal;
-        let dir = self.random_in_hemisphere(*incident) + *normal;
-        if dir.len() < 0.001f64 {
-            // return Ray::new(*hit, *normal);
-            return None;
+        Matte {
+            albedo,
+            vertex_colors: false,
+        }
+    }
+
+    /// Takes the albedo from the vertex colours of the object hit, `albedo` is left for objects without any
+    pub fn with_vertex_colors(self) -> Self {
+        Matte {
+            vertex_colors: true,
+            ..self
         }
-        Some(Ray::new(*hit, dir))
     }
 }
 
 impl Material for Matte {
-    type Albedo = RgbColor;
-    fn scatter(&self, hit_ray: &Ray, hit: &Intersection) -> Option<Ray> {
-        self.lambertian_diffuse(&hit_ray.direction, &hit.normal, &hit.point)
+    fn sample(&self, wo: Vector, hit: &Intersection, sampler: &mut dyn Sampler) -> Option<BsdfSample> {
+        let wi = sampling::cosine_hemisphere(sampler.get_2d());
+        let pdf = self.pdf(wo, wi, hit);
+        if pdf == 0f64 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, hit),
+            pdf,
+            specular: false,
+        })
+    }
+
+    // Lamberts cosine law
+    // RgbColor ∝ dot(I,N)
+    // I = I0*Kd*dot(I,N)/(|I|*|N|)
+    // N is the normal of the surface
+    // I is the incident vector
+    // As the angle increases, the light gets weaker
+    //
+    // The BRDF is the albedo over π in every direction on the side of the surface the ray came from. Sampling with a
+    // density of cosθ/π cancels the cosine and the 1/π out, leaving the albedo as the weight of the bounce.
+    fn eval(&self, wo: Vector, wi: Vector, hit: &Intersection) -> RgbColor {
+        if wo.z <= 0f64 || wi.z <= 0f64 {
+            return RgbColor::default();
+        }
+        self.albedo_at(hit) * FRAC_1_PI
+    }
+
+    fn pdf(&self, wo: Vector, wi: Vector, _hit: &Intersection) -> f64 {
+        if wo.z <= 0f64 {
+            return 0f64;
+        }
+        sampling::cosine_hemisphere_pdf(wi.z)
     }
 
-    fn albedo(&self) -> &Self::Albedo {
-        &self.albedo
+    fn albedo(&self) -> RgbColor {
+        self.albedo
     }
-}
\ No newline at end of file
+
+    fn albedo_at(&self, hit: &Intersection) -> RgbColor {
+        match hit.color {
+            Some(color) if self.vertex_colors => color,
+            _ => self.albedo,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::vec3::Vector as Point;
+    use crate::scene::sampler::IndependentSampler;
+
+    #[test]
+    fn test_sample_is_cosine_weighted_around_normal() {
+        // The bounce leaves on the side of the normal whatever the outgoing direction, with a mean cosine of 2/3, and
+        // every sample is weighed by the albedo
+        let albedo = RgbColor::new(0.5f64, 0.25f64, 1f64);
+        let matte = Matte::new(albedo);
+        let hit = Intersection::new(Point::default(), Vector::new(0f64, 0f64, 1f64), 1f64, true);
+        let mut sampler = IndependentSampler::new(3);
+        let samples = 20_000;
+        let mut cosines = 0f64;
+        for wo in [Vector::new(0f64, 0f64, 1f64), Vector::unit(Vector::new(-1f64, 0.1f64, 0.2f64))] {
+            for _ in 0..samples {
+                let sample = matte.sample(wo, &hit, &mut sampler).unwrap();
+                assert!(sample.wi.z >= 0f64 && !sample.specular);
+                assert_eq!(sample.f, matte.eval(wo, sample.wi, &hit));
+                assert_eq!(sample.pdf, matte.pdf(wo, sample.wi, &hit));
+                assert!((sample.f * (sample.wi.z / sample.pdf) - albedo).len() < 1e-9);
+                cosines += sample.wi.z;
+            }
+        }
+        assert!((cosines / (2 * samples) as f64 - 2f64 / 3f64).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_no_light_through_the_surface() {
+        let matte = Matte::new(RgbColor::new(0.5f64, 0.5f64, 0.5f64));
+        let hit = Intersection::new(Point::default(), Vector::new(0f64, 0f64, 1f64), 1f64, true);
+        let below = Vector::unit(Vector::new(0.3f64, 0f64, -1f64));
+        assert_eq!(matte.eval(Vector::new(0f64, 0f64, 1f64), below, &hit), RgbColor::default());
+        assert_eq!(matte.pdf(Vector::new(0f64, 0f64, 1f64), below, &hit), 0f64);
+    }
+}
@@ -1,9 +1,10 @@
-use crate::scene::material::Material;
+use crate::scene::material::{BsdfSample, Material};
 use crate::color::rgb::RgbColor;
+use crate::geometry::frame::Frame;
+use crate::geometry::sampling;
 use crate::geometry::vec3::Vector;
-use crate::geometry::vec3::Vector as Point;
 use crate::scene::intersect::Intersection;
-use crate::scene::ray::Ray;
+use crate::scene::sampler::Sampler;
 
 pub struct Metal {
     albedo: RgbColor,
@@ -19,20 +20,123 @@ impl Metal {
         }
     }
 
-    fn reflect(&self, hit: &Point, incident: &Vector, normal: &Vector) -> Ray {
-        let mut reflection = *incident - *normal * Vector::dot(*incident, *normal) * 2f64;
-        reflection = reflection + self.random_in_unit_sphere()*self.fuzz;
-        Ray::new(*hit, reflection)
+    // Mirror reflection of `wo` about the normal
+    fn reflect(wo: Vector) -> Vector {
+        Vector::new(-wo.x, -wo.y, wo.z)
+    }
+
+    // Phong exponent of the lobe around the mirror direction, the same roughness mapping as the Blinn-Phong
+    // exponents of OBJ materials: a fuzz of 1 spreads the lobe over the whole hemisphere around the mirror direction,
+    // and the exponent grows without bound as the fuzz goes to 0
+    fn exponent(&self) -> f64 {
+        2f64 / (self.fuzz * self.fuzz) - 2f64
+    }
+
+    fn is_mirror(&self) -> bool {
+        self.fuzz <= 0f64
     }
 }
 
+// Mirror reflection, blurred by a Phong lobe around the mirror direction as wide as the fuzz. The BRDF is the albedo
+// times the density of the lobe over the cosine of the light, so that sampling the lobe weighs every bounce by the
+// albedo. Directions of the lobe below the surface are absorbed.
 impl Material for Metal {
-    type Albedo = RgbColor;
-    fn scatter(&self, hit_ray: &Ray, hit: &Intersection) -> Option<Ray> {
-        Some(self.reflect(&hit.point, &hit_ray.direction, &hit.normal))
+    fn sample(&self, wo: Vector, hit: &Intersection, sampler: &mut dyn Sampler) -> Option<BsdfSample> {
+        // 1. A perfect mirror has a single direction to reflect the light from
+        let mirror = Self::reflect(wo);
+        if self.is_mirror() {
+            return Some(BsdfSample {
+                wi: mirror,
+                f: self.albedo * (1f64 / mirror.z),
+                pdf: 1f64,
+                specular: true,
+            });
+        }
+
+        // 2. Otherwise a direction of the lobe, absorbed when below the surface
+        let wi = Frame::from_normal(mirror).to_world(sampling::phong_lobe(sampler.get_2d(), self.exponent()));
+        let pdf = self.pdf(wo, wi, hit);
+        if pdf == 0f64 {
+            return None;
+        }
+        Some(BsdfSample {
+            wi,
+            f: self.eval(wo, wi, hit),
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn eval(&self, wo: Vector, wi: Vector, hit: &Intersection) -> RgbColor {
+        if self.is_mirror() || wi.z <= 0f64 {
+            return RgbColor::default();
+        }
+        self.albedo * (self.pdf(wo, wi, hit) / wi.z)
+    }
+
+    fn pdf(&self, wo: Vector, wi: Vector, _hit: &Intersection) -> f64 {
+        if self.is_mirror() || wo.z <= 0f64 || wi.z <= 0f64 {
+            return 0f64;
+        }
+        sampling::phong_lobe_pdf(Vector::dot(wi, Self::reflect(wo)), self.exponent())
     }
 
-    fn albedo(&self) -> &Self::Albedo {
-        &self.albedo
+    fn albedo(&self) -> RgbColor {
+        self.albedo
     }
-}
\ No newline at end of file
+
+    fn is_specular(&self) -> bool {
+        self.is_mirror()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::vec3::Vector as Point;
+    use crate::scene::sampler::IndependentSampler;
+
+    fn hit() -> Intersection {
+        Intersection::new(Point::default(), Vector::new(0f64, 0f64, 1f64), 1f64, true)
+    }
+
+    #[test]
+    fn test_mirror_is_specular() {
+        let albedo = RgbColor::new(0.9f64, 0.8f64, 0.7f64);
+        let mirror = Metal::new(albedo, 0f64);
+        let wo = Vector::unit(Vector::new(1f64, 2f64, 3f64));
+        let sample = mirror.sample(wo, &hit(), &mut IndependentSampler::new(0)).unwrap();
+        assert!(sample.specular);
+        assert!((sample.wi - Vector::new(-wo.x, -wo.y, wo.z)).len() < 1e-12);
+        assert!((sample.f * (sample.wi.z / sample.pdf) - albedo).len() < 1e-12);
+        assert_eq!(mirror.eval(wo, sample.wi, &hit()), RgbColor::default());
+        assert_eq!(mirror.pdf(wo, sample.wi, &hit()), 0f64);
+        assert!(mirror.is_specular() && !Metal::new(albedo, 0.1f64).is_specular());
+    }
+
+    #[test]
+    fn test_fuzzy_samples_match_eval_and_pdf() {
+        // Every sample kept is weighed by the albedo, the fuzzier the metal the further from the mirror direction
+        let albedo = RgbColor::new(0.9f64, 0.8f64, 0.7f64);
+        let wo = Vector::unit(Vector::new(0.5f64, 0f64, 1f64));
+        let mirror = Vector::new(-wo.x, -wo.y, wo.z);
+        let mut sampler = IndependentSampler::new(1);
+        let mut spread = Vec::new();
+        for fuzz in [0.05f64, 0.3f64, 1f64] {
+            let metal = Metal::new(albedo, fuzz);
+            let mut cosines = 0f64;
+            for _ in 0..10_000 {
+                let Some(sample) = metal.sample(wo, &hit(), &mut sampler) else {
+                    continue;
+                };
+                assert!(sample.wi.z > 0f64 && !sample.specular);
+                assert_eq!(sample.pdf, metal.pdf(wo, sample.wi, &hit()));
+                assert!((sample.f - metal.eval(wo, sample.wi, &hit())).len() < 1e-12);
+                assert!((sample.f * (sample.wi.z / sample.pdf) - albedo).len() < 1e-9);
+                cosines += Vector::dot(sample.wi, mirror);
+            }
+            spread.push(cosines);
+        }
+        assert!(spread[0] > spread[1] && spread[1] > spread[2]);
+    }
+}
@@ -1,4 +1,12 @@
+pub mod background;
+pub mod bvh;
 pub mod intersect;
+pub mod light;
 pub mod ray;
+pub mod sampler;
 pub mod camera;
-pub mod material;
\ No newline at end of file
+pub mod film;
+pub mod description;
+pub mod material;
+pub mod obj;
+pub mod ply;
\ No newline at end of file
@@ -0,0 +1,610 @@
+use crate::color::rgb::RgbColor;
+use crate::geometry::mesh::Mesh;
+use crate::geometry::vec3::Vector;
+use crate::geometry::vec3::Vector as Point;
+use crate::scene::material::dielectric::Dielectric;
+use crate::scene::material::light::DiffuseLight;
+use crate::scene::material::matte::Matte;
+use crate::scene::material::metal::Metal;
+use crate::scene::material::Material;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Errors reading a Wavefront OBJ model or its MTL material libraries. Apart from `Io` every error carries the file
+/// and the 1-based line it was found on.
+#[derive(Debug)]
+pub enum ObjError {
+    /// A model or material library could not be read
+    Io { path: PathBuf, source: std::io::Error },
+    /// A statement has the wrong number of values, or a value that is not a number
+    Syntax { path: PathBuf, line: usize, message: String },
+    /// A face refers to a vertex, texture coordinate or normal that is not declared before it
+    IndexOutOfRange {
+        path: PathBuf,
+        line: usize,
+        element: &'static str,
+        index: i64,
+    },
+    /// `usemtl` names a material that none of the libraries declare
+    UnknownMaterial { path: PathBuf, line: usize, name: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            ObjError::Syntax { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+            ObjError::IndexOutOfRange {
+                path,
+                line,
+                element,
+                index,
+            } => write!(f, "{}:{}: no {} with index {}", path.display(), line, element, index),
+            ObjError::UnknownMaterial { path, line, name } => {
+                write!(f, "{}:{}: no material named `{}`", path.display(), line, name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Loads a Wavefront OBJ model, with the material libraries it names, as one mesh per material
+///
+/// Vertices, texture coordinates, normals and faces are read, polygons with more than three vertices are split into
+/// a fan of triangles. Faces with texture coordinates or normals on every corner and faces without go to separate
+/// meshes, so a material can have up to four. Groups, smoothing groups, lines and any other statements are ignored.
+/// Faces before the first `usemtl` get the material of an MTL entry that sets nothing, a grey `Matte`.
+///
+/// MTL materials are mapped onto the closest material this renderer has, with their colours converted from sRGB to
+/// linear apart from the emission:
+/// * an emissive colour `Ke` makes a `DiffuseLight`
+/// * a dissolve `d` below 1, or a transparent illumination model (4, 6, 7 or 9), makes a `Dielectric` of index
+///   `Ni`, tinted by the transmission filter `Tf`
+/// * the reflective illumination model 3, or a black diffuse colour with a specular one, makes a `Metal` of colour
+///   `Ks`, the shinier the specular exponent `Ns` the less fuzzy
+/// * anything else is a `Matte` of diffuse colour `Kd`
+///
+/// # Arguments
+/// * `path` - the OBJ file, material libraries are looked up relative to its directory
+///
+/// # Returns
+/// * the meshes, ready to be added to a world
+/// * `ObjError` naming the file and line of the first malformed statement
+///
+/// # Examples
+/// ```no_run
+/// # use rusttracing::scene::obj;
+/// # use rusttracing::world::World;
+/// let mut world = World::new();
+/// for mesh in obj::load("teapot.obj").unwrap() {
+///     world.add(Box::new(mesh));
+/// }
+/// ```
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Mesh>, ObjError> {
+    let path = path.as_ref();
+    parse(&read(path)?, path)
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+// A material as declared in an MTL file, with the spec's defaults for anything left out
+#[derive(Debug, Clone, PartialEq)]
+struct MtlMaterial {
+    diffuse: RgbColor,
+    specular: RgbColor,
+    emission: RgbColor,
+    transmission: Option<RgbColor>,
+    shininess: f64,
+    refractive_index: f64,
+    dissolve: f64,
+    illumination: Option<u32>,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        MtlMaterial {
+            diffuse: RgbColor::new(0.8f64, 0.8f64, 0.8f64).to_linear(),
+            specular: RgbColor::default(),
+            emission: RgbColor::default(),
+            transmission: None,
+            shininess: 0f64,
+            refractive_index: 1.5f64,
+            dissolve: 1f64,
+            illumination: None,
+        }
+    }
+}
+
+impl MtlMaterial {
+    fn build(&self) -> Arc<dyn Material> {
+        let black = |c: RgbColor| c.x <= 0f64 && c.y <= 0f64 && c.z <= 0f64;
+
+        if !black(self.emission) {
+            return Arc::new(DiffuseLight::new(self.emission));
+        }
+        if self.dissolve < 1f64 || matches!(self.illumination, Some(4 | 6 | 7 | 9)) {
+            let tint = self.transmission.unwrap_or(RgbColor::new(1f64, 1f64, 1f64));
+            return Arc::new(Dielectric::new(tint, self.refractive_index));
+        }
+        if self.illumination == Some(3) || (black(self.diffuse) && !black(self.specular)) {
+            // Blinn-Phong exponent to roughness, an exponent of 0 is fully rough and 1000 (the MTL maximum) almost
+            // a perfect mirror
+            let fuzz = (2f64 / (self.shininess.max(0f64) + 2f64)).sqrt();
+            return Arc::new(Metal::new(self.specular, fuzz));
+        }
+        Arc::new(Matte::new(self.diffuse))
+    }
+}
+
+// Splits the file into statements, yielding the line number, keyword and arguments of each non-empty line
+fn statements(source: &str) -> impl Iterator<Item = (usize, &str, Vec<&str>)> {
+    source.lines().enumerate().filter_map(|(i, line)| {
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let keyword = words.next()?;
+        Some((i + 1, keyword, words.collect()))
+    })
+}
+
+// Reads between `min` and `max` numbers from the arguments of a statement
+fn numbers(
+    path: &Path,
+    line: usize,
+    keyword: &str,
+    args: &[&str],
+    min: usize,
+    max: usize,
+) -> Result<Vec<f64>, ObjError> {
+    let syntax = |message: String| ObjError::Syntax {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+    if args.len() < min || args.len() > max {
+        let expected = if min == max {
+            min.to_string()
+        } else {
+            format!("{} to {}", min, max)
+        };
+        return Err(syntax(format!("`{}` takes {} values, found {}", keyword, expected, args.len())));
+    }
+    args.iter()
+        .map(|arg| {
+            arg.parse::<f64>()
+                .map_err(|_| syntax(format!("`{}` is not a number in `{}`", arg, keyword)))
+        })
+        .collect()
+}
+
+fn parse_mtl(source: &str, path: &Path) -> Result<HashMap<String, MtlMaterial>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (line, keyword, args) in statements(source) {
+        if keyword == "newmtl" {
+            if args.len() != 1 {
+                return Err(ObjError::Syntax {
+                    path: path.to_path_buf(),
+                    line,
+                    message: "`newmtl` takes a single name".to_string(),
+                });
+            }
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((args[0].to_string(), MtlMaterial::default()));
+            continue;
+        }
+
+        let Some((_, material)) = current.as_mut() else {
+            // Nothing but comments may come before the first material
+            return Err(ObjError::Syntax {
+                path: path.to_path_buf(),
+                line,
+                message: format!("`{}` outside of a material, expected `newmtl` first", keyword),
+            });
+        };
+        let color = |args: &[&str]| {
+            // A single value is a grey, anything but one or three values is not a colour
+            if args.len() == 2 {
+                return Err(ObjError::Syntax {
+                    path: path.to_path_buf(),
+                    line,
+                    message: format!("`{}` takes 1 or 3 values, found 2", keyword),
+                });
+            }
+            numbers(path, line, keyword, args, 1, 3).map(|c| match c[..] {
+                [g] => RgbColor::new(g, g, g),
+                _ => RgbColor::new(c[0], c[1], c[2]),
+            })
+        };
+        match keyword {
+            // Reflectances are picked in sRGB by whoever made the material, emission is already radiance
+            "Kd" => material.diffuse = color(&args)?.to_linear(),
+            "Ks" => material.specular = color(&args)?.to_linear(),
+            "Ke" => material.emission = color(&args)?,
+            "Tf" => material.transmission = Some(color(&args)?.to_linear()),
+            "Ns" => material.shininess = numbers(path, line, keyword, &args, 1, 1)?[0],
+            "Ni" => material.refractive_index = numbers(path, line, keyword, &args, 1, 1)?[0],
+            "d" => material.dissolve = numbers(path, line, keyword, &args, 1, 1)?[0],
+            "Tr" => material.dissolve = 1f64 - numbers(path, line, keyword, &args, 1, 1)?[0],
+            "illum" => {
+                material.illumination = Some(args.first().and_then(|a| a.parse().ok()).ok_or_else(|| {
+                    ObjError::Syntax {
+                        path: path.to_path_buf(),
+                        line,
+                        message: "`illum` takes an illumination model number".to_string(),
+                    }
+                })?)
+            }
+            // Ambient colour, texture maps and the like have nothing to map onto
+            _ => {}
+        }
+    }
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+    Ok(materials)
+}
+
+// One vertex of a face, as indices into the position, texture coordinate and normal lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct FaceVertex {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+// The faces of one mesh, over vertices of their own. OBJ faces pick a position, texture coordinate and normal for
+// every corner separately, each combination in use becomes one vertex of the mesh shared by the faces around it.
+struct Group {
+    material: Arc<dyn Material>,
+    positions: Vec<Point>,
+    uvs: Vec<(f64, f64)>,
+    normals: Vec<Vector>,
+    faces: Vec<[usize; 3]>,
+    vertex_of: HashMap<FaceVertex, usize>,
+}
+
+impl Group {
+    fn new(material: Arc<dyn Material>) -> Self {
+        Group {
+            material,
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            faces: Vec::new(),
+            vertex_of: HashMap::new(),
+        }
+    }
+
+    // Index of the mesh vertex for a face vertex, added from the lists of the file the first time it is used
+    fn vertex(&mut self, corner: FaceVertex, positions: &[Point], uvs: &[(f64, f64)], normals: &[Vector]) -> usize {
+        *self.vertex_of.entry(corner).or_insert_with(|| {
+            self.positions.push(positions[corner.position]);
+            if let Some(uv) = corner.uv {
+                self.uvs.push(uvs[uv]);
+            }
+            if let Some(normal) = corner.normal {
+                self.normals.push(normals[normal]);
+            }
+            self.positions.len() - 1
+        })
+    }
+
+    fn build(self) -> Mesh {
+        let mut mesh = Mesh::new(self.positions, self.faces, self.material);
+        if !self.uvs.is_empty() {
+            mesh = mesh.with_uvs(self.uvs);
+        }
+        if !self.normals.is_empty() {
+            mesh = mesh.with_normals(self.normals);
+        }
+        mesh
+    }
+}
+
+fn parse(source: &str, path: &Path) -> Result<Vec<Mesh>, ObjError> {
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let syntax = |line: usize, message: String| ObjError::Syntax {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let mut positions: Vec<Point> = Vec::new();
+    let mut uvs: Vec<(f64, f64)> = Vec::new();
+    let mut normals: Vec<Vector> = Vec::new();
+    let mut library: HashMap<String, MtlMaterial> = HashMap::new();
+    // Faces grouped by material and by whether they have texture coordinates and normals, in the order they are
+    // first seen. None is the default material.
+    let mut groups: Vec<Group> = Vec::new();
+    let mut group_of: HashMap<(Option<String>, bool, bool), usize> = HashMap::new();
+    let mut materials: HashMap<Option<String>, Arc<dyn Material>> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for (line, keyword, args) in statements(source) {
+        match keyword {
+            "v" => {
+                // The optional fourth value is a weight only used by rational curves
+                let v = numbers(path, line, keyword, &args, 3, 4)?;
+                positions.push(Point::new(v[0], v[1], v[2]));
+            }
+            "vt" => {
+                let vt = numbers(path, line, keyword, &args, 1, 3)?;
+                uvs.push((vt[0], *vt.get(1).unwrap_or(&0f64)));
+            }
+            "vn" => {
+                let vn = numbers(path, line, keyword, &args, 3, 3)?;
+                normals.push(Vector::new(vn[0], vn[1], vn[2]));
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(syntax(line, "`mtllib` takes at least one file name".to_string()));
+                }
+                for name in args {
+                    let mtl_path = directory.join(name);
+                    library.extend(parse_mtl(&read(&mtl_path)?, &mtl_path)?);
+                }
+            }
+            "usemtl" => {
+                if args.len() != 1 {
+                    return Err(syntax(line, "`usemtl` takes a single name".to_string()));
+                }
+                if !library.contains_key(args[0]) {
+                    return Err(ObjError::UnknownMaterial {
+                        path: path.to_path_buf(),
+                        line,
+                        name: args[0].to_string(),
+                    });
+                }
+                current = Some(args[0].to_string());
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(syntax(line, format!("a face needs at least 3 vertices, found {}", args.len())));
+                }
+                let mut face = args
+                    .iter()
+                    .map(|arg| face_vertex(path, line, arg, (positions.len(), uvs.len(), normals.len())))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // Attributes are only kept when every corner has them
+                let has_uvs = face.iter().all(|corner| corner.uv.is_some());
+                let has_normals = face.iter().all(|corner| corner.normal.is_some());
+                for corner in face.iter_mut() {
+                    corner.uv = corner.uv.filter(|_| has_uvs);
+                    corner.normal = corner.normal.filter(|_| has_normals);
+                }
+
+                let group = *group_of.entry((current.clone(), has_uvs, has_normals)).or_insert_with(|| {
+                    let material = materials.entry(current.clone()).or_insert_with(|| match &current {
+                        Some(name) => library[name].build(),
+                        None => MtlMaterial::default().build(),
+                    });
+                    groups.push(Group::new(material.clone()));
+                    groups.len() - 1
+                });
+                let group = &mut groups[group];
+                let corners: Vec<usize> = face
+                    .into_iter()
+                    .map(|corner| group.vertex(corner, &positions, &uvs, &normals))
+                    .collect();
+
+                // Fan triangulation around the first vertex, exact for the convex polygons OBJ allows
+                for i in 1..corners.len() - 1 {
+                    group.faces.push([corners[0], corners[i], corners[i + 1]]);
+                }
+            }
+            // Groups, objects, smoothing groups, lines, points and free-form geometry
+            _ => {}
+        }
+    }
+
+    Ok(groups.into_iter().map(Group::build).collect())
+}
+
+// Reads a face vertex, `v`, `v/vt`, `v//vn` or `v/vt/vn`. Indices start at 1, negative ones count back from the
+// last element declared so far. `counts` is the number of positions, texture coordinates and normals declared.
+fn face_vertex(path: &Path, line: usize, arg: &str, counts: (usize, usize, usize)) -> Result<FaceVertex, ObjError> {
+    let resolve = |element: &'static str, index: &str, count: usize| -> Result<usize, ObjError> {
+        let index: i64 = index.parse().map_err(|_| ObjError::Syntax {
+            path: path.to_path_buf(),
+            line,
+            message: format!("`{}` is not a valid face vertex", arg),
+        })?;
+        let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(ObjError::IndexOutOfRange {
+                path: path.to_path_buf(),
+                line,
+                element,
+                index,
+            });
+        }
+        Ok(resolved as usize)
+    };
+
+    let mut parts = arg.split('/');
+    let position = resolve("vertex", parts.next().unwrap_or(""), counts.0)?;
+    let uv = match parts.next() {
+        None | Some("") => None,
+        Some(index) => Some(resolve("texture coordinate", index, counts.1)?),
+    };
+    let normal = match parts.next() {
+        None => None,
+        Some(index) => Some(resolve("normal", index, counts.2)?),
+    };
+    if parts.next().is_some() {
+        return Err(ObjError::Syntax {
+            path: path.to_path_buf(),
+            line,
+            message: format!("`{}` is not a valid face vertex", arg),
+        });
+    }
+    Ok(FaceVertex { position, uv, normal })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::intersect::Intersect;
+    use crate::scene::ray::Ray;
+
+    const QUAD: &str = "\
+# A unit square in the z = -1 plane
+v 0 0 -1
+v 1 0 -1
+v 1 1 -1
+v 0 1 -1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1 4/4/1
+";
+
+    fn parse_str(source: &str) -> Result<Vec<Mesh>, ObjError> {
+        parse(source, Path::new("test.obj"))
+    }
+
+    #[test]
+    fn test_obj_polygon_is_triangulated() {
+        let meshes = parse_str(QUAD).unwrap();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].len(), 2);
+        assert_eq!(meshes[0].material().albedo(), RgbColor::new(0.8f64, 0.8f64, 0.8f64).to_linear());
+
+        let ray = Ray::new(Point::new(0.75, 0.5, 0f64), Vector::new(0f64, 0f64, -1f64));
+        let hit = meshes[0].intersects(&ray, (0.001, f64::INFINITY)).unwrap();
+        assert!((hit.t - 1f64).abs() < 1e-12);
+        assert!((hit.uv.0 - 0.75).abs() < 1e-12 && (hit.uv.1 - 0.5).abs() < 1e-12);
+        assert_eq!(hit.normal, Vector::new(0f64, 0f64, 1f64));
+    }
+
+    #[test]
+    fn test_obj_face_vertex_forms() {
+        let source = "v 0 0 -1\nv 1 0 -1\nv 0 1 -1\nvt 0 0\nvn 0 0 1\nf 1 2 3\nf 1//1 2//1 3//1\nf -3/1 -2/1 -1/1\n";
+        // Without attributes, with normals and with texture coordinates, one mesh each
+        let meshes = parse_str(source).unwrap();
+        assert_eq!(meshes.iter().map(|mesh| mesh.len()).collect::<Vec<_>>(), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn test_obj_errors_have_line_numbers() {
+        match parse_str("v 0 0 0\nv 1 0 0\n\nv 0 1\n") {
+            Err(ObjError::Syntax { line: 4, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_str("v 0 0 0\nv 1 0 0\nv 0 x 0\n") {
+            Err(ObjError::Syntax { line: 3, message, .. }) => assert!(message.contains("`x`")),
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_str("v 0 0 0\nv 1 0 0\nv 0 1 0\n# comment\nf 1 2 4\n") {
+            Err(ObjError::IndexOutOfRange {
+                line: 5,
+                element: "vertex",
+                index: 4,
+                ..
+            }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_str("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2/1 3/1\n") {
+            Err(ObjError::IndexOutOfRange {
+                line: 4,
+                element: "texture coordinate",
+                ..
+            }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_str("v 0 0 0\nf 1 1\n") {
+            Err(ObjError::Syntax { line: 2, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_str("usemtl missing\n") {
+            Err(ObjError::UnknownMaterial { line: 1, name, .. }) => assert_eq!(name, "missing"),
+            other => panic!("unexpected {:?}", other),
+        }
+        let error = parse_str("v 0 0 0\nf 0 1 1\n").unwrap_err();
+        assert_eq!(error.to_string(), "test.obj:2: no vertex with index 0");
+    }
+
+    #[test]
+    fn test_mtl_material_mapping() {
+        let source = "\
+newmtl clay
+Kd 0.5 0.4 0.3
+
+newmtl chrome
+illum 3
+Kd 0 0 0
+Ks 0.9 0.9 0.9
+Ns 1000
+
+newmtl glass
+illum 7
+Tf 0.9 1.0 0.9
+Ni 1.33
+
+newmtl lamp
+Ke 4
+";
+        let materials = parse_mtl(source, Path::new("test.mtl")).unwrap();
+        assert_eq!(materials["clay"].build().albedo(), RgbColor::new(0.5, 0.4, 0.3).to_linear());
+        assert_eq!(materials["chrome"].build().albedo(), RgbColor::new(0.9, 0.9, 0.9).to_linear());
+        assert_eq!(materials["glass"].refractive_index, 1.33);
+        assert_eq!(materials["glass"].build().albedo(), RgbColor::new(0.9, 1f64, 0.9).to_linear());
+        assert_eq!(materials["lamp"].emission, RgbColor::new(4f64, 4f64, 4f64));
+
+        match parse_mtl("newmtl a\nKd 1 2 3 4\n", Path::new("test.mtl")) {
+            Err(ObjError::Syntax { line: 2, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_mtl("newmtl a\nd 1\nKd 0.5 0.4\n", Path::new("test.mtl")) {
+            Err(ObjError::Syntax { line: 3, message, .. }) => assert!(message.contains("1 or 3")),
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_mtl("Kd 1 1 1\n", Path::new("test.mtl")) {
+            Err(ObjError::Syntax { line: 1, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_obj_load_with_materials() {
+        let directory = crate::temp_path("test_obj_load_with_materials");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("test.mtl"), "newmtl red\nKd 1 0 0\nnewmtl mirror\nillum 3\nKs 1 1 1\n").unwrap();
+        let model = "mtllib test.mtl\nv 0 0 -1\nv 1 0 -1\nv 0 1 -1\nusemtl red\nf 1 2 3\nusemtl mirror\nf 1 3 2\n\
+                     usemtl red\nf 3 2 1\n";
+        fs::write(directory.join("test.obj"), model).unwrap();
+
+        let meshes = load(directory.join("test.obj")).unwrap();
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].len(), 2);
+        assert_eq!(meshes[0].material().albedo(), RgbColor::new(1f64, 0f64, 0f64));
+        assert_eq!(meshes[1].len(), 1);
+        assert_eq!(meshes[1].material().albedo(), RgbColor::new(1f64, 1f64, 1f64));
+
+        // A library that is not there
+        fs::write(directory.join("broken.obj"), "mtllib missing.mtl\n").unwrap();
+        assert!(matches!(load(directory.join("broken.obj")), Err(ObjError::Io { .. })));
+    }
+}
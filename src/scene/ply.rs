@@ -0,0 +1,598 @@
+use crate::color::rgb::RgbColor;
+use crate::geometry::mesh::Mesh;
+use crate::geometry::vec3::Vector;
+use crate::geometry::vec3::Vector as Point;
+use crate::scene::material::Material;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Errors reading a PLY mesh
+#[derive(Debug)]
+pub enum PlyError {
+    /// The file could not be read
+    Io { path: PathBuf, source: std::io::Error },
+    /// The header is malformed or describes something other than a mesh, `line` is 1-based
+    Header { line: usize, message: String },
+    /// The data of an element does not match the header, `index` is the 0-based position of the element within its
+    /// kind, e.g. the 12th face
+    Data {
+        element: String,
+        index: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            PlyError::Header { line, message } => write!(f, "header line {}: {}", line, message),
+            PlyError::Data {
+                element,
+                index,
+                message,
+            } => write!(f, "{} {}: {}", element, index, message),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlyError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Loads a PLY mesh, in the ASCII or either of the binary encodings
+///
+/// The `vertex` element gives the positions (`x`, `y`, `z`) and optionally the normals (`nx`, `ny`, `nz`), colours
+/// (`red`, `green`, `blue`) and texture coordinates (`u`, `v` or `s`, `t`) of the vertices. The `face` element lists
+/// the vertices of every face in `vertex_indices`, polygons are split into a fan of triangles. The vertices have to
+/// come before the faces, as they do in every file in the wild. Any other element or property is skipped.
+///
+/// Colours stored as integers are scaled to [0, 1] and decoded from sRGB to linear. They end up in the intersections
+/// with the mesh, a `Matte` made `with_vertex_colors` uses them as its albedo.
+///
+/// # Arguments
+/// * `path` - the PLY file
+/// * `material` - the material of the whole mesh
+///
+/// # Examples
+/// ```no_run
+/// # use rusttracing::color::rgb::RgbColor;
+/// # use rusttracing::scene::material::matte::Matte;
+/// # use rusttracing::scene::ply;
+/// # use rusttracing::world::World;
+/// # use std::sync::Arc;
+/// let material = Matte::new(RgbColor::new(0.8, 0.8, 0.8)).with_vertex_colors();
+/// let mut world = World::new();
+/// world.add(Box::new(ply::load("scan.ply", Arc::new(material)).unwrap()));
+/// ```
+pub fn load<P: AsRef<Path>>(path: P, material: Arc<dyn Material>) -> Result<Mesh, PlyError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| PlyError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse(&bytes, material)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Option<Self> {
+        // Both the original names and the sized ones are in use
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // Largest value of the integer types, colours stored as integers are fractions of it
+    fn full_scale(self) -> f64 {
+        match self {
+            Scalar::I8 => i8::MAX as f64,
+            Scalar::U8 => u8::MAX as f64,
+            Scalar::I16 => i16::MAX as f64,
+            Scalar::U16 => u16::MAX as f64,
+            Scalar::I32 => i32::MAX as f64,
+            Scalar::U32 => u32::MAX as f64,
+            Scalar::F32 | Scalar::F64 => 1f64,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar { name: String, scalar: Scalar },
+    List { name: String, count: Scalar, item: Scalar },
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn scalar(&self, name: &str) -> Option<(usize, Scalar)> {
+        self.properties.iter().enumerate().find_map(|(i, property)| match property {
+            Property::Scalar { name: n, scalar } if n == name => Some((i, *scalar)),
+            _ => None,
+        })
+    }
+
+    fn list(&self, names: &[&str]) -> Option<usize> {
+        self.properties.iter().position(|property| match property {
+            Property::List { name, .. } => names.contains(&name.as_str()),
+            _ => false,
+        })
+    }
+
+    // Fewest bytes an instance of the element can be stored in, a character per value in ASCII and an empty list
+    // for every list
+    fn min_size(&self, format: Format) -> usize {
+        self.properties
+            .iter()
+            .map(|property| match (format, property) {
+                (Format::Ascii, _) => 1,
+                (_, Property::Scalar { scalar, .. }) => scalar.size(),
+                (_, Property::List { count, .. }) => count.size(),
+            })
+            .sum()
+    }
+}
+
+// Returns the format, the elements and the offset of the first byte after the header
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize), PlyError> {
+    let header = |line: usize, message: &str| PlyError::Header {
+        line,
+        message: message.to_string(),
+    };
+
+    let mut offset = 0;
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in 1.. {
+        let Some(end) = bytes[offset..].iter().position(|&b| b == b'\n') else {
+            return Err(header(line, "the header does not end with `end_header`"));
+        };
+        let text = std::str::from_utf8(&bytes[offset..offset + end])
+            .map_err(|_| header(line, "the header is not text"))?;
+        offset += end + 1;
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["ply"] if line == 1 => {}
+            _ if line == 1 => return Err(header(line, "not a PLY file, it does not start with `ply`")),
+            ["format", name, "1.0"] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(header(line, &format!("unknown format `{}`", name))),
+                })
+            }
+            ["format", ..] => return Err(header(line, "expected `format <encoding> 1.0`")),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| header(line, &format!("`{}` is not an element count", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let count = Scalar::from_name(count)
+                    .filter(|s| !matches!(s, Scalar::F32 | Scalar::F64))
+                    .ok_or_else(|| header(line, &format!("`{}` is not an integer type", count)))?;
+                let item = Scalar::from_name(item).ok_or_else(|| header(line, &format!("unknown type `{}`", item)))?;
+                elements
+                    .last_mut()
+                    .ok_or_else(|| header(line, "property before any element"))?
+                    .properties
+                    .push(Property::List {
+                        name: name.to_string(),
+                        count,
+                        item,
+                    });
+            }
+            ["property", scalar, name] => {
+                let scalar =
+                    Scalar::from_name(scalar).ok_or_else(|| header(line, &format!("unknown type `{}`", scalar)))?;
+                elements
+                    .last_mut()
+                    .ok_or_else(|| header(line, "property before any element"))?
+                    .properties
+                    .push(Property::Scalar {
+                        name: name.to_string(),
+                        scalar,
+                    });
+            }
+            ["end_header"] => {
+                let format = format.ok_or_else(|| header(line, "no `format` line before `end_header`"))?;
+                return Ok((format, elements, offset));
+            }
+            _ => return Err(header(line, &format!("unexpected `{}`", text.trim()))),
+        }
+    }
+    unreachable!("the header loop only ends by returning")
+}
+
+// Reads the values of the elements one after the other, whatever the encoding
+struct Reader<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn read(&mut self, scalar: Scalar) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            return self.read_ascii(scalar);
+        }
+
+        let size = scalar.size();
+        let raw = self
+            .bytes
+            .get(self.offset..self.offset + size)
+            .ok_or_else(|| "unexpected end of file".to_string())?;
+        self.offset += size;
+        let mut buffer = [0u8; 8];
+        buffer[..size].copy_from_slice(raw);
+        if self.format == Format::BinaryBigEndian {
+            buffer[..size].reverse();
+        }
+        // The buffer is now little endian whatever the file was
+        Ok(match scalar {
+            Scalar::I8 => buffer[0] as i8 as f64,
+            Scalar::U8 => buffer[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(buffer),
+        })
+    }
+
+    fn read_ascii(&mut self, scalar: Scalar) -> Result<f64, String> {
+        let rest = &self.bytes[self.offset..];
+        let start = rest
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .ok_or_else(|| "unexpected end of file".to_string())?;
+        let len = rest[start..]
+            .iter()
+            .position(|b| b.is_ascii_whitespace())
+            .unwrap_or(rest.len() - start);
+        self.offset += start + len;
+        let word = String::from_utf8_lossy(&rest[start..start + len]);
+        let value: f64 = word.parse().map_err(|_| format!("`{}` is not a number", word))?;
+        if !matches!(scalar, Scalar::F32 | Scalar::F64) && value.fract() != 0f64 {
+            return Err(format!("`{}` is not an integer", word));
+        }
+        Ok(value)
+    }
+}
+
+// The vertex data the mesh is built from
+#[derive(Default)]
+struct Vertices {
+    positions: Vec<Point>,
+    normals: Option<Vec<Vector>>,
+    colors: Option<Vec<RgbColor>>,
+    uvs: Option<Vec<(f64, f64)>>,
+}
+
+fn parse(bytes: &[u8], material: Arc<dyn Material>) -> Result<Mesh, PlyError> {
+    let (format, elements, offset) = parse_header(bytes)?;
+    let mut reader = Reader { format, bytes, offset };
+
+    let mut vertices = Vertices::default();
+    // Faces as triangle corner indices, checked against the vertices read before them
+    let mut faces: Vec<[usize; 3]> = Vec::new();
+
+    for element in &elements {
+        // 1. Find the properties we know about
+        let (xyz, normal, color, uv, indices) = match element.name.as_str() {
+            "vertex" => {
+                let xyz = ["x", "y", "z"].map(|n| element.scalar(n));
+                let [Some(x), Some(y), Some(z)] = xyz else {
+                    return Err(PlyError::Data {
+                        element: element.name.clone(),
+                        index: 0,
+                        message: "vertices need `x`, `y` and `z` properties".to_string(),
+                    });
+                };
+                let all = |names: [&str; 3]| match names.map(|n| element.scalar(n)) {
+                    [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+                    _ => None,
+                };
+                let uv = match (element.scalar("u"), element.scalar("v")) {
+                    (Some(u), Some(v)) => Some([u, v]),
+                    _ => match (element.scalar("s"), element.scalar("t")) {
+                        (Some(s), Some(t)) => Some([s, t]),
+                        _ => None,
+                    },
+                };
+                (
+                    Some([x, y, z]),
+                    all(["nx", "ny", "nz"]),
+                    all(["red", "green", "blue"]),
+                    uv,
+                    None,
+                )
+            }
+            "face" => (None, None, None, None, element.list(&["vertex_indices", "vertex_index"])),
+            _ => (None, None, None, None, None),
+        };
+
+        // 2. Check the count of the header against the data left before reserving room for it, a corrupt count would
+        // otherwise ask for more memory than there is. Elements without properties take no room at all.
+        let min_size = element.min_size(format);
+        if min_size == 0 {
+            continue;
+        }
+        let fit = (bytes.len() - reader.offset) / min_size;
+        if element.count > fit {
+            return Err(PlyError::Data {
+                element: element.name.clone(),
+                index: fit,
+                message: format!("the file ends before the {} elements of the header", element.count),
+            });
+        }
+        if normal.is_some() {
+            vertices.normals = Some(Vec::with_capacity(element.count));
+        }
+        if color.is_some() {
+            vertices.colors = Some(Vec::with_capacity(element.count));
+        }
+        if uv.is_some() {
+            vertices.uvs = Some(Vec::with_capacity(element.count));
+        }
+
+        // 3. Read every instance of the element, keeping the values of those properties
+        let mut values = vec![0f64; element.properties.len()];
+        let mut list = Vec::new();
+        for index in 0..element.count {
+            let data = |message: String| PlyError::Data {
+                element: element.name.clone(),
+                index,
+                message,
+            };
+            for (i, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::Scalar { scalar, .. } => values[i] = reader.read(*scalar).map_err(data)?,
+                    Property::List { count, item, .. } => {
+                        let n = reader.read(*count).map_err(data)?;
+                        let keep = Some(i) == indices;
+                        if keep {
+                            list.clear();
+                        }
+                        for _ in 0..n as usize {
+                            let value = reader.read(*item).map_err(data)?;
+                            if keep {
+                                list.push(value);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let get = |[a, b, c]: [(usize, Scalar); 3]| Vector::new(values[a.0], values[b.0], values[c.0]);
+            if let Some(xyz) = xyz {
+                vertices.positions.push(get(xyz));
+            }
+            if let (Some(normal), Some(normals)) = (normal, vertices.normals.as_mut()) {
+                normals.push(get(normal));
+            }
+            if let (Some(color), Some(colors)) = (color, vertices.colors.as_mut()) {
+                // Integer colours are sRGB encoded like any 8 or 16-bit image, floats are taken as linear
+                let scalar = color[0].1;
+                let value = get(color) / scalar.full_scale();
+                colors.push(if matches!(scalar, Scalar::F32 | Scalar::F64) {
+                    value
+                } else {
+                    value.to_linear()
+                });
+            }
+            if let (Some([u, v]), Some(uvs)) = (uv, vertices.uvs.as_mut()) {
+                uvs.push((values[u.0], values[v.0]));
+            }
+            if indices.is_some() {
+                if list.len() < 3 {
+                    return Err(data(format!("a face needs at least 3 vertices, found {}", list.len())));
+                }
+                if let Some(negative) = list.iter().find(|&&i| i < 0f64) {
+                    return Err(data(format!("negative vertex index {}", negative)));
+                }
+                if let Some(fraction) = list.iter().find(|&&i| i.fract() != 0f64) {
+                    return Err(data(format!("vertex index {} is not an integer", fraction)));
+                }
+                let vertex_count = vertices.positions.len();
+                if let Some(out) = list.iter().find(|&&i| i >= vertex_count as f64) {
+                    return Err(data(format!(
+                        "vertex index {} out of range, there are {} vertices",
+                        out, vertex_count
+                    )));
+                }
+                // Fan triangulation around the first vertex
+                for i in 1..list.len() - 1 {
+                    faces.push([list[0] as usize, list[i] as usize, list[i + 1] as usize]);
+                }
+            }
+        }
+    }
+
+    // 4. Build the mesh
+    let mut mesh = Mesh::new(vertices.positions, faces, material);
+    if let Some(normals) = vertices.normals {
+        mesh = mesh.with_normals(normals);
+    }
+    if let Some(colors) = vertices.colors {
+        mesh = mesh.with_colors(colors);
+    }
+    if let Some(uvs) = vertices.uvs {
+        mesh = mesh.with_uvs(uvs);
+    }
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::intersect::Intersect;
+    use crate::scene::material::matte::Matte;
+    use crate::scene::ray::Ray;
+
+    // A unit square in the z = -1 plane, red at the origin and blue elsewhere
+    const HEADER: &str = "ply\nformat {} 1.0\ncomment made by hand\nelement vertex 4\nproperty float x\n\
+                          property float y\nproperty float z\nproperty uchar red\nproperty uchar green\n\
+                          property uchar blue\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n";
+    const POSITIONS: [[f32; 3]; 4] = [[0.0, 0.0, -1.0], [1.0, 0.0, -1.0], [1.0, 1.0, -1.0], [0.0, 1.0, -1.0]];
+    const COLORS: [[u8; 3]; 4] = [[255, 0, 0], [0, 0, 255], [0, 0, 255], [0, 0, 255]];
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Matte::new(RgbColor::new(0.5, 0.5, 0.5)).with_vertex_colors())
+    }
+
+    fn square(format: &str, big_endian: bool) -> Vec<u8> {
+        let mut bytes = HEADER.replace("{}", format).into_bytes();
+        let int = |i: i32| if big_endian { i.to_be_bytes() } else { i.to_le_bytes() };
+        for (position, color) in POSITIONS.iter().zip(COLORS) {
+            for p in position {
+                bytes.extend(if big_endian { p.to_be_bytes() } else { p.to_le_bytes() });
+            }
+            bytes.extend(color);
+        }
+        bytes.push(4);
+        for i in 0..4 {
+            bytes.extend(int(i));
+        }
+        bytes
+    }
+
+    fn check_square(mesh: &Mesh) {
+        assert_eq!(mesh.len(), 2);
+        let ray = Ray::new(Point::new(0f64, 0f64, 0f64), Vector::new(0f64, 0f64, -1f64));
+        let hit = mesh.intersects(&ray, (0.001, f64::INFINITY)).unwrap();
+        assert!((hit.t - 1f64).abs() < 1e-12);
+        assert_eq!(hit.color, Some(RgbColor::new(1f64, 0f64, 0f64)));
+        assert_eq!(mesh.material().albedo_at(&hit), RgbColor::new(1f64, 0f64, 0f64));
+    }
+
+    #[test]
+    fn test_ply_encodings() {
+        let ascii = HEADER.replace("{}", "ascii")
+            + "0 0 -1 255 0 0\n1 0 -1 0 0 255\n1 1 -1 0 0 255\n0 1 -1 0 0 255\n4 0 1 2 3\n";
+        check_square(&parse(ascii.as_bytes(), material()).unwrap());
+        check_square(&parse(&square("binary_little_endian", false), material()).unwrap());
+        check_square(&parse(&square("binary_big_endian", true), material()).unwrap());
+    }
+
+    #[test]
+    fn test_ply_normals_and_unknown_elements() {
+        let source = "ply\nformat ascii 1.0\nelement vertex 3\nproperty double x\nproperty double y\n\
+                      property double z\nproperty float nx\nproperty float ny\nproperty float nz\n\
+                      property int flags\nelement face 1\nproperty list uchar uint vertex_index\n\
+                      element edge 1\nproperty int vertex1\nproperty int vertex2\nend_header\n\
+                      0 0 -1 0 0 1 7\n1 0 -1 0 0 1 7\n0 1 -1 0 0 1 7\n3 0 1 2\n0 1\n";
+        let mesh = parse(source.as_bytes(), material()).unwrap();
+        assert_eq!(mesh.len(), 1);
+        let ray = Ray::new(Point::new(0.25, 0.25, 0f64), Vector::new(0f64, 0f64, -1f64));
+        let hit = mesh.intersects(&ray, (0.001, f64::INFINITY)).unwrap();
+        assert_eq!(hit.normal, Vector::new(0f64, 0f64, 1f64));
+        // Without vertex colours the material falls back to its albedo
+        assert_eq!(hit.color, None);
+        assert_eq!(mesh.material().albedo_at(&hit), RgbColor::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_ply_errors() {
+        match parse(b"obj\n", material()) {
+            Err(PlyError::Header { line: 1, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty quad x\nend_header\n", material()) {
+            Err(PlyError::Header { line: 4, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n", material()) {
+            Err(PlyError::Header { line: 5, .. }) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        // Truncated binary data
+        let mut truncated = square("binary_little_endian", false);
+        truncated.truncate(truncated.len() - 2);
+        match parse(&truncated, material()) {
+            Err(PlyError::Data { element, index: 0, .. }) => assert_eq!(element, "face"),
+            other => panic!("unexpected {:?}", other),
+        }
+        // Counts the data cannot hold, which must not be reserved for
+        let ascii = "ply\nformat ascii 1.0\nelement vertex 18446744073709551615\nproperty float x\nproperty float y\n\
+                     property float z\nend_header\n0 0 -1\n";
+        match parse(ascii.as_bytes(), material()) {
+            Err(PlyError::Data { element, index: 2, .. }) => assert_eq!(element, "vertex"),
+            other => panic!("unexpected {:?}", other),
+        }
+        let mut binary = square("binary_little_endian", false);
+        let header = HEADER.replace("{}", "binary_little_endian");
+        binary.splice(..header.len(), header.replace("face 1", "face 10000000000").into_bytes());
+        match parse(&binary, material()) {
+            Err(PlyError::Data { element, index: 17, message }) => {
+                assert_eq!(element, "face");
+                assert_eq!(message, "the file ends before the 10000000000 elements of the header");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        // A face pointing past the last vertex
+        let ascii = HEADER.replace("{}", "ascii")
+            + "0 0 -1 255 0 0\n1 0 -1 0 0 255\n1 1 -1 0 0 255\n0 1 -1 0 0 255\n4 0 1 2 4\n";
+        let error = parse(ascii.as_bytes(), material()).unwrap_err();
+        assert_eq!(error.to_string(), "face 0: vertex index 4 out of range, there are 4 vertices");
+        // Float indices have to be whole numbers
+        let floats = HEADER.replace("{}", "ascii").replace("uchar int vertex_indices", "uchar float vertex_indices")
+            + "0 0 -1 255 0 0\n1 0 -1 0 0 255\n1 1 -1 0 0 255\n0 1 -1 0 0 255\n4 0 1.7 2 3\n";
+        let error = parse(floats.as_bytes(), material()).unwrap_err();
+        assert_eq!(error.to_string(), "face 0: vertex index 1.7 is not an integer");
+    }
+}
@@ -0,0 +1,594 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Source of the random values a render is made of
+///
+/// Every sample of a pixel starts with `start_sample`, then draws the values it needs one or two dimensions at a
+/// time. A 1D value uses up one dimension and a 2D value two. The same dimensions have to serve the same purpose in
+/// every sample for the samplers to spread them well: the position within the pixel is always the first 2D value, the
+/// point on the lens the second, and every bounce starts at a fixed dimension set with `set_dimension`.
+///
+/// # Examples
+/// ```
+/// # use rusttracing::scene::sampler::{Sampler, SamplerKind};
+/// let mut sampler = SamplerKind::Sobol.build(16, 42);
+/// for index in 0..16 {
+///     sampler.start_sample((3, 4), index);
+///     let (dx, dy) = sampler.get_2d();
+///     assert!((0.0..1.0).contains(&dx) && (0.0..1.0).contains(&dy));
+/// }
+/// ```
+pub trait Sampler: Send {
+    /// Moves on to sample `index` of `pixel`, starting again from the first dimension
+    fn start_sample(&mut self, pixel: (u32, u32), index: usize);
+
+    /// Skips to `dimension` of the current sample
+    fn set_dimension(&mut self, dimension: usize);
+
+    /// Next value of the sample, in [0, 1)
+    fn get_1d(&mut self) -> f64;
+
+    /// Next pair of values of the sample, in [0, 1) x [0, 1)
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+/// The samplers a render can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SamplerKind {
+    /// Uniform random values, with the most noise
+    Independent,
+    /// Jittered strata, in a random order in every dimension
+    Stratified,
+    /// Halton sequence with Owen scrambled digits
+    Halton,
+    /// Owen scrambled Sobol sequence, the least noise for most scenes
+    #[default]
+    Sobol,
+}
+
+impl SamplerKind {
+    /// A sampler of this kind for renders with `samples_per_pixel` samples, scrambled by `seed`
+    pub fn build(self, samples_per_pixel: usize, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(samples_per_pixel, seed)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+impl fmt::Display for SamplerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SamplerKind::Independent => "independent",
+            SamplerKind::Stratified => "stratified",
+            SamplerKind::Halton => "halton",
+            SamplerKind::Sobol => "sobol",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!(
+                "unknown sampler `{}`, expected independent, stratified, halton or sobol",
+                s
+            )),
+        }
+    }
+}
+
+// Hashing
+// The scrambles and permutations of the samplers are drawn from hashes of the seed, the pixel and the dimension,
+// which spares storing them and makes every pixel independent of the others.
+
+// SplitMix64 finaliser, every bit of the input affects every bit of the output
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Hash of a few values, e.g. a seed and the coordinates of a pixel
+pub fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x9e3779b97f4a7c15, |h, &v| mix(h ^ mix(v.wrapping_add(0x9e3779b97f4a7c15))))
+}
+
+// Value in [0, 1) from the top 53 bits of a hash
+fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 * (1f64 / (1u64 << 53) as f64)
+}
+
+fn pixel_hash(seed: u64, pixel: (u32, u32)) -> u64 {
+    hash(&[seed, pixel.0 as u64, pixel.1 as u64])
+}
+
+/// Independent uniform random values
+///
+/// Every sample draws from its own random number generator, seeded with a hash of the seed, the pixel and the
+/// sample index. A sample takes the same values whatever was drawn before it.
+pub struct IndependentSampler {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        IndependentSampler {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: usize) {
+        self.rng = StdRng::seed_from_u64(hash(&[pixel_hash(self.seed, pixel), index as u64]));
+    }
+
+    fn set_dimension(&mut self, _dimension: usize) {}
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.gen_range(0f64..1f64)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+/// Jittered stratified sampling
+///
+/// Every dimension is cut into as many strata as there are samples per pixel, and each sample of a pixel takes a
+/// random point of a different stratum. 2D values are stratified over a grid of at least as many cells. The strata
+/// are shuffled independently in every dimension so that the dimensions do not line up with each other.
+pub struct StratifiedSampler {
+    samples_per_pixel: usize,
+    seed: u64,
+    pixel_seed: u64,
+    index: usize,
+    dimension: usize,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        StratifiedSampler {
+            samples_per_pixel: samples_per_pixel.max(1),
+            seed,
+            pixel_seed: seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    // Stratum of the current sample among `strata`, and the jitter within it
+    fn stratum(&self, strata: usize) -> (usize, f64) {
+        let dimension_seed = hash(&[self.pixel_seed, self.dimension as u64]);
+        let stratum = permute(
+            (self.index % strata) as u32,
+            strata as u32,
+            dimension_seed as u32,
+        );
+        (stratum as usize, to_unit(hash(&[dimension_seed, self.index as u64])))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: usize) {
+        self.pixel_seed = pixel_hash(self.seed, pixel);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn set_dimension(&mut self, dimension: usize) {
+        self.dimension = dimension;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (stratum, jitter) = self.stratum(self.samples_per_pixel);
+        self.dimension += 1;
+        (stratum as f64 + jitter) / self.samples_per_pixel as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        // As square a grid as will hold every sample
+        let columns = (self.samples_per_pixel as f64).sqrt().ceil() as usize;
+        let rows = self.samples_per_pixel.div_ceil(columns);
+        let (stratum, jitter_x) = self.stratum(columns * rows);
+        let jitter_y = to_unit(hash(&[self.pixel_seed, self.dimension as u64, self.index as u64, 1]));
+        self.dimension += 2;
+        (
+            ((stratum % columns) as f64 + jitter_x) / columns as f64,
+            ((stratum / columns) as f64 + jitter_y) / rows as f64,
+        )
+    }
+}
+
+// Element `i` of a random permutation of [0, l) picked by `p`, Kensler's hash based permutation from Correlated
+// Multi-Jittered Sampling. Values past l are cycled through the permutation of the next power of two until they
+// fall back in range.
+fn permute(mut i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            return (i.wrapping_add(p)) % l;
+        }
+    }
+}
+
+/// Halton low discrepancy sequence
+///
+/// Dimension d of sample i is the radical inverse of i in the d-th prime base, the digits of i in that base mirrored
+/// around the decimal point. Any run of consecutive samples spreads evenly over every dimension, and the first two
+/// cover the pixel as well as a grid would. The digits are Owen scrambled differently for every pixel, which breaks
+/// up the correlation between the higher dimensions, whose bases are larger than the number of samples, and between
+/// neighbouring pixels. Dimensions past the primes tabulated fall back to random values.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel_seed: u64,
+    index: usize,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        HaltonSampler {
+            seed,
+            pixel_seed: seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+// The first primes, bases of the Halton dimensions
+fn primes() -> &'static [u64] {
+    static PRIMES: OnceLock<Vec<u64>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        // Sieve of Eratosthenes, up to the 1000th prime
+        let limit = 7920;
+        let mut composite = vec![false; limit];
+        let mut primes = Vec::new();
+        for n in 2..limit {
+            if !composite[n] {
+                primes.push(n as u64);
+                (n * n..limit).step_by(n).for_each(|m| composite[m] = true);
+            }
+        }
+        primes
+    })
+}
+
+// Owen scrambled radical inverse of `index` in `base`. Every digit goes through a random permutation picked by the
+// digits before it, so the samples keep their strata while landing anywhere within them. The zeros past the last
+// digit of the index are scrambled as well, down to a millionth, which keeps the first million samples stratified.
+// Below that the scrambled digits are as good as a uniform random value.
+fn scrambled_radical_inverse(base: u64, index: u64, seed: u64) -> f64 {
+    let inv_base = 1f64 / base as f64;
+    let (mut value, mut scale) = (0f64, inv_base);
+    let (mut digits, mut prefix, mut position) = (index, 0, 1);
+    while digits > 0 || scale > 1e-6 {
+        let permutation = hash(&[seed, position, prefix]) as u32;
+        value += permute((digits % base) as u32, base as u32, permutation) as f64 * scale;
+        digits /= base;
+        prefix = index % (position * base);
+        position *= base;
+        scale *= inv_base;
+    }
+    value += to_unit(hash(&[seed, position, index])) * scale * base as f64;
+    value.min(1f64 - f64::EPSILON / 2f64)
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: usize) {
+        self.pixel_seed = pixel_hash(self.seed, pixel);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn set_dimension(&mut self, dimension: usize) {
+        self.dimension = dimension;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension_seed = hash(&[self.pixel_seed, self.dimension as u64]);
+        let value = match primes().get(self.dimension) {
+            Some(&base) => scrambled_radical_inverse(base, self.index as u64, dimension_seed),
+            None => to_unit(hash(&[dimension_seed, self.index as u64])),
+        };
+        self.dimension += 1;
+        value
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+/// Sobol low discrepancy sequence with Owen scrambling
+///
+/// Burley's hash based Owen scrambling from Practical Hash-based Owen Scrambling. Every 1D or 2D value comes from the
+/// first two Sobol dimensions, with the order of the samples shuffled and the values scrambled by hashes of the
+/// pixel and dimension. Shuffling pads the dimensions with independent copies of the best two, scrambling keeps
+/// their stratification over every power of two block of samples while making them random.
+pub struct SobolSampler {
+    seed: u64,
+    pixel_seed: u64,
+    index: u32,
+    dimension: usize,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        SobolSampler {
+            seed,
+            pixel_seed: seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    // The sample index shuffled and the hash scrambling values of the current dimension
+    fn shuffled_index(&self) -> (u32, u64) {
+        let dimension_seed = hash(&[self.pixel_seed, self.dimension as u64]);
+        (nested_uniform_scramble(self.index, dimension_seed as u32), dimension_seed)
+    }
+}
+
+// Direction numbers of the second Sobol dimension, generated by the primitive polynomial x + 1, m_k = 2 m_k-1 ^ m_k-1
+const SOBOL_DIRECTIONS: [u32; 32] = {
+    let mut directions = [0u32; 32];
+    let mut m = 1u32;
+    let mut k = 0;
+    while k < 32 {
+        directions[k] = m << (31 - k);
+        m ^= m << 1;
+        k += 1;
+    }
+    directions
+};
+
+// First two dimensions of the Sobol sequence, as 32 bit fractions. The first one is the van der Corput sequence.
+fn sobol(index: u32) -> (u32, u32) {
+    let mut y = 0;
+    let mut i = index;
+    let mut k = 0;
+    while i != 0 {
+        if i & 1 == 1 {
+            y ^= SOBOL_DIRECTIONS[k];
+        }
+        i >>= 1;
+        k += 1;
+    }
+    (index.reverse_bits(), y)
+}
+
+// Laine and Karras' hash, each bit only depends on the bits below it so that reversed it is an Owen scramble
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn fraction(x: u32) -> f64 {
+    x as f64 * (1f64 / (1u64 << 32) as f64)
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: usize) {
+        self.pixel_seed = pixel_hash(self.seed, pixel);
+        self.index = index as u32;
+        self.dimension = 0;
+    }
+
+    fn set_dimension(&mut self, dimension: usize) {
+        self.dimension = dimension;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (index, dimension_seed) = self.shuffled_index();
+        self.dimension += 1;
+        fraction(nested_uniform_scramble(sobol(index).0, hash(&[dimension_seed, 0]) as u32))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, dimension_seed) = self.shuffled_index();
+        self.dimension += 2;
+        let (x, y) = sobol(index);
+        (
+            fraction(nested_uniform_scramble(x, hash(&[dimension_seed, 0]) as u32)),
+            fraction(nested_uniform_scramble(y, hash(&[dimension_seed, 1]) as u32)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [SamplerKind; 4] = [
+        SamplerKind::Independent,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+    ];
+
+    // Values of `dimension` for every sample of a pixel
+    fn samples_2d(sampler: &mut dyn Sampler, count: usize, pixel: (u32, u32), dimension: usize) -> Vec<(f64, f64)> {
+        (0..count)
+            .map(|index| {
+                sampler.start_sample(pixel, index);
+                sampler.set_dimension(dimension);
+                sampler.get_2d()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sobol_sequence() {
+        // The unscrambled first two dimensions
+        let expected = [(0f64, 0f64), (0.5, 0.5), (0.25, 0.75), (0.75, 0.25), (0.125, 0.625), (0.625, 0.125)];
+        for (index, (x, y)) in expected.iter().enumerate() {
+            let (sx, sy) = sobol(index as u32);
+            assert_eq!((fraction(sx), fraction(sy)), (*x, *y));
+        }
+    }
+
+    #[test]
+    fn test_halton_sequence() {
+        // With no scrambling, base 3 goes 0, 1/3, 2/3, 1/9, 4/9...
+        let radical_inverse = |index: u64| {
+            let (mut value, mut scale, mut i) = (0f64, 1f64 / 3f64, index);
+            while i > 0 {
+                value += (i % 3) as f64 * scale;
+                i /= 3;
+                scale /= 3f64;
+            }
+            value
+        };
+        assert!((radical_inverse(4) - 4f64 / 9f64).abs() < 1e-12);
+        assert_eq!(&primes()[..6], &[2, 3, 5, 7, 11, 13]);
+        assert_eq!(primes().len(), 1000);
+        // Scrambling permutes the strata of every digit, so the first 9 samples still fall in different ninths
+        let mut ninths: Vec<usize> = (0..9)
+            .map(|i| (scrambled_radical_inverse(3, i, 42) * 9f64) as usize)
+            .collect();
+        ninths.sort();
+        assert_eq!(ninths, (0..9).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_permute_is_a_permutation() {
+        for l in [1, 2, 7, 16, 100] {
+            for p in [0, 1, 0xdeadbeef] {
+                let mut values: Vec<u32> = (0..l).map(|i| permute(i, l, p)).collect();
+                values.sort();
+                assert_eq!(values, (0..l).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn test_low_discrepancy_samplers_stratify() {
+        // 16 samples of any of the 2D dimensions put one sample in each cell of a 4 x 4 grid
+        for kind in [SamplerKind::Stratified, SamplerKind::Sobol] {
+            let mut sampler = kind.build(16, 7);
+            for (pixel, dimension) in [((0, 0), 0), ((5, 3), 2), ((1, 1), 31)] {
+                let mut cells: Vec<usize> = samples_2d(sampler.as_mut(), 16, pixel, dimension)
+                    .iter()
+                    .map(|(x, y)| (x * 4f64) as usize * 4 + (y * 4f64) as usize)
+                    .collect();
+                cells.sort();
+                assert_eq!(cells, (0..16).collect::<Vec<_>>(), "{} dimension {}", kind, dimension);
+            }
+        }
+        // Halton stratifies its first two dimensions over 2 x 3 grids
+        let mut sampler = SamplerKind::Halton.build(6, 7);
+        let mut cells: Vec<usize> = samples_2d(sampler.as_mut(), 6, (2, 2), 0)
+            .iter()
+            .map(|(x, y)| (x * 2f64) as usize * 3 + (y * 3f64) as usize)
+            .collect();
+        cells.sort();
+        assert_eq!(cells, (0..6).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_samplers_are_uniform() {
+        // Mean and variance of a uniform variable on [0, 1) are 1/2 and 1/12 in every dimension. The high Halton
+        // dimensions only spread out over many pixels, their bases being larger than the number of samples.
+        for kind in KINDS {
+            let mut sampler = kind.build(16, 3);
+            for dimension in [0, 3, 10, 200] {
+                let mut values = Vec::new();
+                for pixel in 0..256 {
+                    for index in 0..16 {
+                        sampler.start_sample((pixel, 0), index);
+                        sampler.set_dimension(dimension);
+                        let value = sampler.get_1d();
+                        assert!((0f64..1f64).contains(&value));
+                        values.push(value);
+                    }
+                }
+                let mean = values.iter().sum::<f64>() / values.len() as f64;
+                let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+                assert!((mean - 0.5).abs() < 0.05, "{} dimension {}: mean {}", kind, dimension, mean);
+                assert!((variance - 1f64 / 12f64).abs() < 0.015, "{} dimension {}", kind, dimension);
+            }
+        }
+    }
+
+    #[test]
+    fn test_samples_only_depend_on_seed_pixel_and_index() {
+        for kind in KINDS {
+            let (mut a, mut b) = (kind.build(8, 11), kind.build(8, 11));
+            // Draw a few values from other samples first with one of them
+            samples_2d(a.as_mut(), 8, (4, 4), 0);
+            a.get_1d();
+            let first = samples_2d(a.as_mut(), 8, (2, 3), 6);
+            assert_eq!(first, samples_2d(b.as_mut(), 8, (2, 3), 6), "{}", kind);
+            assert_ne!(first, samples_2d(kind.build(8, 12).as_mut(), 8, (2, 3), 6), "{}", kind);
+        }
+    }
+
+    #[test]
+    fn test_pixels_are_decorrelated() {
+        for kind in KINDS {
+            let mut sampler = kind.build(4, 3);
+            let a = samples_2d(sampler.as_mut(), 4, (0, 0), 4);
+            let b = samples_2d(sampler.as_mut(), 4, (1, 0), 4);
+            assert_ne!(a, b, "{}", kind);
+        }
+    }
+
+    #[test]
+    fn test_parse_sampler_kinds() {
+        for kind in KINDS {
+            assert_eq!(kind.to_string().parse(), Ok(kind));
+        }
+        assert!("random".parse::<SamplerKind>().is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::color::antialias::ReconstructionFilter;
+use crate::scene::sampler::SamplerKind;
+use crate::world::LightSampling;
+use std::path::PathBuf;
+
+/// Everything about a render that is not part of the scene itself
+///
+/// Quick previews and final renders of the same scene only differ in their settings.
+///
+/// # Examples
+/// ```
+/// use rusttracing::settings::RenderSettings;
+/// // Small noisy preview
+/// let preview = RenderSettings {
+///     samples_per_pixel: 4,
+///     max_depth: 8,
+///     ..RenderSettings::with_aspect(200, 16f64 / 9f64)
+/// };
+/// assert_eq!(preview.height, 112);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    /// Width of the render in pixels
+    pub width: u32,
+    /// Height of the render in pixels
+    pub height: u32,
+    /// Number of rays traced for every pixel
+    pub samples_per_pixel: usize,
+    /// Where the samples are taken, in the pixel, on the lens and at every bounce
+    pub sampler: SamplerKind,
+    /// How the samples are weighed into pixels, a plain average of the samples in each pixel by default
+    pub filter: ReconstructionFilter,
+    /// Number of bounces after which a ray is considered to have lost all its energy
+    pub max_depth: usize,
+    /// How light sampling and BSDF sampling are combined to find the light coming straight from the lights
+    pub light_sampling: LightSampling,
+    /// Seed of the samplers, renders with the same seed and settings are identical. A random one is picked when
+    /// `None`
+    pub seed: Option<u64>,
+    /// Number of worker threads rendering the image
+    pub threads: usize,
+    /// File the render is written to
+    pub output: PathBuf,
+}
+
+impl RenderSettings {
+    pub fn new(width: u32, height: u32) -> Self {
+        RenderSettings {
+            width,
+            height,
+            samples_per_pixel: 50,
+            sampler: SamplerKind::default(),
+            filter: ReconstructionFilter::default(),
+            max_depth: 50,
+            light_sampling: LightSampling::default(),
+            seed: None,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            output: PathBuf::from("render.ppm"),
+        }
+    }
+
+    /// Settings for a render of the given width, with the height following from the aspect ratio
+    pub fn with_aspect(width: u32, aspect_ratio: f64) -> Self {
+        RenderSettings::new(width, (width as f64 / aspect_ratio) as u32)
+    }
+
+    pub fn aspect_ratio(&self) -> f64 {
+        self.width as f64 / self.height as f64
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings::with_aspect(400, 16f64 / 9f64)
+    }
+}
@@ -1,68 +1,224 @@
 use crate::color::rgb::RgbColor;
+use crate::geometry::frame::Frame;
 use crate::geometry::vec3::Vector;
 use crate::geometry::vec3::Vector as Point;
+use crate::scene::background::Background;
+use crate::scene::bvh::Bvh;
 use crate::scene::camera::Camera;
+use crate::scene::film::Film;
 use crate::scene::intersect::Intersect;
 use crate::scene::intersect::Intersection;
+use crate::scene::light::{Light, LightSample};
 use crate::scene::material::Material;
 use crate::scene::ray::Ray;
-use rand::Rng;
+use crate::scene::sampler::Sampler;
+use crate::settings::RenderSettings;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
 use std::vec::Vec;
 
+// Sample dimensions used up by the camera, the position within the pixel and on the lens
+const CAMERA_DIMENSIONS: usize = 4;
+// Sample dimensions used at every bounce to pick a light and a point on it
+const LIGHT_DIMENSIONS: usize = 3;
+// Sample dimensions used at every bounce by the material to sample a direction
+const BSDF_DIMENSIONS: usize = 2;
+// Sample dimensions reserved for every bounce, lighting first and then the bounce itself
+const BOUNCE_DIMENSIONS: usize = LIGHT_DIMENSIONS + BSDF_DIMENSIONS;
+// Rays leaving a surface ignore hits closer than this, which would be the surface itself through rounding errors
+const RAY_EPSILON: f64 = 0.01;
+
+/// How `World` combines the two ways of finding the light coming straight from lights and emissive objects: sampling
+/// a light at every hit, and the bounce sampled from the BSDF hitting an emitter
+///
+/// Either is enough for a correct render, but each is noisy where the other is good: BSDF samples rarely find small
+/// lights from rough surfaces, and light samples rarely fall in the narrow highlights of glossy ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LightSampling {
+    /// Only count the light found by the bounces. Lights of no size are still sampled, nothing else can find them
+    Bsdf,
+    /// Only count the light found by sampling lights
+    Lights,
+    /// Count both, weighed with the power heuristic of multiple importance sampling, which favours whichever of the
+    /// two was the more likely to find the light
+    #[default]
+    Mis,
+}
+
+impl fmt::Display for LightSampling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LightSampling::Bsdf => "bsdf",
+            LightSampling::Lights => "lights",
+            LightSampling::Mis => "mis",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for LightSampling {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bsdf" => Ok(LightSampling::Bsdf),
+            "lights" => Ok(LightSampling::Lights),
+            "mis" => Ok(LightSampling::Mis),
+            _ => Err(format!("unknown light sampling `{}`, expected bsdf, lights or mis", s)),
+        }
+    }
+}
+
 /// A virtual world is represented here
 pub struct World {
     contents: Vec<Box<dyn Intersect>>,
+    // Acceleration structure over `contents`, built on the first ray traced after the contents last changed
+    bvh: OnceLock<Bvh>,
+    // Light reaching rays that escape the world
+    background: Background,
+    // Lights of no size, only ever reached by sampling them
+    lights: Vec<Light>,
+    // Indices into `contents` of the objects made of emissive materials, sampled like lights
+    emitters: Vec<usize>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new()
+    }
 }
 
 impl World {
-    const MAX_DEPTH: usize = 50;
-    const SAMPLES_PER_PIXEL: usize = 50;
     /// Returns a new empty world
     ///
     /// # Examples
     /// ```
-    /// use world::World;
+    /// use rusttracing::world::World;
     /// let world = World::new();
     /// ```
     pub fn new() -> Self {
         World {
             contents: Vec::new(),
+            bvh: OnceLock::new(),
+            background: Background::default(),
+            lights: Vec::new(),
+            emitters: Vec::new(),
         }
     }
 
+    /// Sets the light coming from outside the world, a blue sky gradient by default. Use a black
+    /// `Background::Solid` for scenes lit only by emissive materials.
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    /// Adds a point or directional light. Objects made of emissive materials light the world without being added
+    /// here.
+    ///
+    /// # Examples
+    /// ```
+    /// # use rusttracing::color::rgb::RgbColor;
+    /// # use rusttracing::geometry::vec3::Vector as Point;
+    /// # use rusttracing::scene::light::Light;
+    /// # use rusttracing::world::World;
+    /// let mut world = World::new();
+    /// world.add_light(Light::Point {
+    ///     position: Point::new(0.0, 2.0, 0.0),
+    ///     intensity: RgbColor::new(10.0, 10.0, 10.0),
+    /// });
+    /// ```
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
     pub fn size(self) -> usize {
         self.contents.len()
     }
 
-    pub fn render_pixel(&self, camera: &Camera, coords: (u32, u32), dims: (u32, u32)) -> RgbColor {
-        let mut pixel_color = RgbColor::default();
-        let mut rand = rand::thread_rng();
-        for _ in 0..Self::SAMPLES_PER_PIXEL {
-            let u = coords.0 as f64 / ((dims.0 - 1) as f64 + rand.gen_range(0f64..1f64));
-            let v = coords.1 as f64 / ((dims.1 - 1) as f64 + rand.gen_range(0f64..1f64));
-            let ray = camera.get_ray(u, v);
-            pixel_color = pixel_color + self.raytrace(&ray, World::MAX_DEPTH);
+    /// Returns the color of the pixel at `coords` of a render with the given settings, (0, 0) being the bottom
+    /// left pixel. `settings.samples_per_pixel` rays are traced through the pixel and weighed with `settings.filter`.
+    ///
+    /// Unlike `render`, only the samples taken within the pixel count towards it, which lets previews draw pixels
+    /// one at a time.
+    pub fn render_pixel(
+        &self,
+        camera: &Camera,
+        coords: (u32, u32),
+        settings: &RenderSettings,
+        sampler: &mut dyn Sampler,
+    ) -> RgbColor {
+        // A film of a single pixel, with the samples moved to its footprint
+        let mut film = Film::new(1, 1, settings.filter);
+        self.trace_pixel(camera, coords, settings, sampler, |position, color| {
+            film.add_sample((position.0 - coords.0 as f64, position.1 - coords.1 as f64), color)
+        });
+        film.into_image()[0]
+    }
+
+    /// Traces the `settings.samples_per_pixel` samples of the pixel at `coords` and hands each of them to `splat`,
+    /// along with its film position in pixels from the bottom left corner of the image.
+    ///
+    /// Pixel (x, y) covers [x, x + 1) x [y, y + 1) of the film, which maps onto the whole viewport of the camera. Each
+    /// sample is placed within the pixel by the first 2D value the sampler draws for it, and on the lens by the
+    /// second. Bounces draw from their own dimensions after that.
+    fn trace_pixel(
+        &self,
+        camera: &Camera,
+        coords: (u32, u32),
+        settings: &RenderSettings,
+        sampler: &mut dyn Sampler,
+        mut splat: impl FnMut((f64, f64), RgbColor),
+    ) {
+        for index in 0..settings.samples_per_pixel {
+            // 1. Where the sample falls within the pixel and on the lens
+            sampler.start_sample(coords, index);
+            let (dx, dy) = sampler.get_2d();
+            let lens = sampler.get_2d();
+            let position = (coords.0 as f64 + dx, coords.1 as f64 + dy);
+            // 2. Trace the ray through that point of the viewport
+            let ray = camera.get_ray(
+                position.0 / settings.width as f64,
+                position.1 / settings.height as f64,
+                lens,
+            );
+            splat(position, self.raytrace(&ray, settings, sampler));
         }
-        pixel_color = pixel_color * (1f64 / World::SAMPLES_PER_PIXEL as f64);
-        pixel_color
     }
 
     /// Returns the rendering of the world from the input camera viewport as a vector of RgbColor. The render dimension
-    /// is controlled by the input image width and the camera aspenct ratio. 
+    /// is controlled by the render settings, whose aspect ratio should match the camera's.
     /// 
     /// Generates a ray from the each of the pixels of the viewport and traces it in the world. For a given pixel,
-    /// `World::SAMPLES_PER_PIXEL` samples are generated. The color of the rays is then averaged for anti-aliasing.
+    /// `settings.samples_per_pixel` samples are generated at random positions within the pixel. Their colours are
+    /// splatted onto a `Film` which weighs them into pixels with `settings.filter` for anti-aliasing.
+    ///
+    /// The scanlines are handed out one at a time to `settings.threads` worker threads, each with its own sampler.
+    /// Every scanline is splatted onto its own tile of film, and the tiles are merged in order as they come in, so
+    /// that the sums over neighbouring scanlines do not depend on which thread finished first.
+    ///
+    /// The render is deterministic for a given `settings.seed`: the values of every sample only depend on the seed,
+    /// the pixel and the sample index, not on the thread that traces it or on what that thread traced before.
     /// 
     /// # Arguments
     /// * `camera` - Ref to a camera object which controls the viewport settings
-    /// * `img_width` - Width of the render
+    /// * `settings` - Size of the render, sampling and threading settings
     /// 
     /// # Returns
     /// * Vec<RgbColor> - the rendered scene 
     /// 
     /// # Examples
-    /// ```
-    /// const IMG_WIDTH = 800;
+    /// ```no_run
+    /// # use rusttracing::color::rgb::RgbColor;
+    /// # use rusttracing::geometry::sphere::Sphere;
+    /// # use rusttracing::geometry::vec3::Vector as Point;
+    /// # use rusttracing::scene::material::matte::Matte;
+    /// # use rusttracing::world::World;
+    /// # use rusttracing::scene::camera::Camera;
+    /// # use rusttracing::settings::RenderSettings;
     /// let mut world = World::new();
     /// let sphere1 = Sphere::new(
     ///     Point::new(0.0, 0.0, -1.0),
@@ -71,33 +227,57 @@ impl World {
     /// );
     /// world.add(Box::new(sphere1));
     /// let camera = Camera::default();
-    /// let rendered_scene = world.render(&camera, IMG_WIDTH);
+    /// let settings = RenderSettings::with_aspect(800, camera.aspect_ratio);
+    /// let rendered_scene = world.render(&camera, &settings);
     /// ```
-    pub fn render(&self, camera: &Camera, img_width: u32) -> Vec<RgbColor> {
-        // 1. Calculate image height from width using the camera aspect ratio
-        let img_height = (img_width as f64 / camera.aspect_ratio) as u32;
-        // 2. Initialize with capacity because it's known, and avoid reallocation
-        let mut render = Vec::<RgbColor>::with_capacity((img_height * img_width) as usize);
-
-        // TODO: render in multiple threads
-        for y in (0..img_height).rev() {
-            eprint!("Generating line {:#3?}\r", img_height - y);
-            for x in 0..img_width {
-                // Oversample and average with jitter > antialiasing
-                let mut pixel_color = RgbColor::default();
-                let mut rand = rand::thread_rng();
-                for _ in 0..World::SAMPLES_PER_PIXEL {
-                    let u = x as f64 / ((img_width - 1) as f64 + rand.gen_range(0f64..1f64));
-                    let v = y as f64 / ((img_height - 1) as f64 + rand.gen_range(0f64..1f64));
-                    let ray = camera.get_ray(u, v);
-                    pixel_color = pixel_color + self.raytrace(&ray, World::MAX_DEPTH);
+    pub fn render(&self, camera: &Camera, settings: &RenderSettings) -> Vec<RgbColor> {
+        // 1. Image size
+        let (img_width, img_height) = (settings.width, settings.height);
+        let mut film = Film::new(img_width, img_height, settings.filter);
+        // 2. Scanlines are numbered top to bottom, which is the order they are rendered in
+        let next_line = AtomicU32::new(0);
+        let (sender, receiver) = mpsc::channel();
+        let seed = settings.seed.unwrap_or_else(rand::random);
+
+        thread::scope(|scope| {
+            for _ in 0..settings.threads.max(1) {
+                let (next_line, sender) = (&next_line, sender.clone());
+                scope.spawn(move || {
+                    // 3. Every worker pulls the next free scanline until there are none left
+                    let mut sampler = settings.sampler.build(settings.samples_per_pixel, seed);
+                    loop {
+                        let line = next_line.fetch_add(1, Ordering::Relaxed);
+                        if line >= img_height {
+                            break;
+                        }
+                        eprint!("Generating line {:#3?}\r", line + 1);
+                        let y = img_height - 1 - line;
+                        let mut tile = Film::tile(img_width, img_height, settings.filter, y);
+                        for x in 0..img_width {
+                            self.trace_pixel(camera, (x, y), settings, sampler.as_mut(), |position, color| {
+                                tile.add_sample(position, color)
+                            });
+                        }
+                        // The receiver only goes away if the main thread panicked
+                        let _ = sender.send((line, tile));
+                    }
+                });
+            }
+            drop(sender);
+
+            // 4. Merge the tiles in scanline order, holding on to the ones that arrive early
+            let mut pending = BTreeMap::new();
+            let mut next = 0;
+            for (line, tile) in receiver {
+                pending.insert(line, tile);
+                while let Some(tile) = pending.remove(&next) {
+                    film.merge(&tile);
+                    next += 1;
                 }
-                pixel_color = pixel_color * (1f64 / World::SAMPLES_PER_PIXEL as f64);
-                render.push(pixel_color);
             }
-        }
+        });
 
-        return render;
+        film.into_image()
     }
 
     /// Adds an object implementng the Intersect trait to the world. The stored object is used for calculating 
@@ -108,6 +288,11 @@ impl World {
     /// 
     /// # Examples
     /// ```
+    /// # use rusttracing::color::rgb::RgbColor;
+    /// # use rusttracing::geometry::sphere::Sphere;
+    /// # use rusttracing::geometry::vec3::Vector as Point;
+    /// # use rusttracing::scene::material::matte::Matte;
+    /// # use rusttracing::world::World;
     /// let mut world = World::new();
     /// let sphere1 = Sphere::new(
     ///     Point::new(0.0, 0.0, -1.0),
@@ -118,53 +303,199 @@ impl World {
     /// ```
     pub fn add(&mut self, object: Box<dyn Intersect>) {
         // TODO: add a remove method
+        if object.material().is_emissive() {
+            self.emitters.push(self.contents.len());
+        }
         self.contents.push(object);
+        self.bvh = OnceLock::new();
     }
 
     /// Returns the color to be rendered for an input ray in according to the scene setup by the world contents.
-    /// 
-    /// Recursive function call which will trace the input ray in the scene its children until either the `depth`
-    /// is reached or the material absorbs the ray. 
-    /// 
-    /// If an object from the `World::content` vector is intersected by the ray, the scattered ray is calculated
-    /// by the `scatter` function of the `Material` trait.
-    /// 
-    /// If nothing is hit it a gradient is generated along the y-axis.
-    /// 
+    ///
+    /// Follows the path of the ray through the scene until either `settings.max_depth` bounces are reached or a
+    /// material absorbs it. At every bounce the material samples the direction the light comes from with its BSDF,
+    /// and the throughput of the path is weighed by `f * |cos θ| / pdf`, the Monte Carlo estimate of the light
+    /// scattered.
+    ///
+    /// Light straight from the lights and emissive objects can then be found two ways: at every hit on a surface that
+    /// is not specular, by sampling one of them and casting a shadow ray towards it (next event estimation), and by
+    /// the next bounce hitting an emitter. `settings.light_sampling` picks how the two estimates are combined, with
+    /// multiple importance sampling by default.
+    ///
+    /// If nothing is hit the ray gets the colour of the world background.
+    ///
     /// # Arguments
-    /// 
+    ///
     /// * `ray` - Ref to the ray for which the color has to be computed
-    /// * `depth` - the recursion depth for limiting the number of child ray calculations
-    /// 
+    /// * `settings` - render settings, for the maximum number of bounces and the light sampling strategy
+    /// * `sampler` - sampler of the thread tracing the ray
+    ///
     /// # Returns
-    /// 
+    ///
     /// * RgbColor - color to be rendered for the input ray
-    /// 
-    fn raytrace(&self, ray: &Ray, depth: usize) -> RgbColor {
+    ///
+    fn raytrace(&self, ray: &Ray, settings: &RenderSettings, sampler: &mut dyn Sampler) -> RgbColor {
+        let mut radiance = RgbColor::default();
+        let mut throughput = RgbColor::new(1f64, 1f64, 1f64);
+        let mut ray = Ray::new(ray.origin, ray.direction);
+        // Where the last bounce left from and the density it was sampled with. None for camera rays and specular
+        // bounces, which no light sample could have found
+        let mut last_bounce: Option<(Point, f64)> = None;
         // 1. Lose all energy after max hits
-        if depth <= 0 {
+        for bounce in 0..settings.max_depth {
+            // 2. Check if the ray intersects an object in the world, if not the light comes from the background
+            let Some((ray_hit, object)) = self.hit(&ray, (RAY_EPSILON, f64::INFINITY)) else {
+                radiance = radiance + throughput * self.background.color(&ray);
+                break;
+            };
+            let material = object.material();
+
+            // 3. Light given off by the surface itself, weighed against the chance of the last hit sampling it
+            let emitted = material.emitted(&ray_hit);
+            if emitted != RgbColor::default() {
+                let weight = match (settings.light_sampling, last_bounce) {
+                    (_, None) | (LightSampling::Bsdf, _) => 1f64,
+                    (LightSampling::Lights, Some(_)) => 0f64,
+                    (LightSampling::Mis, Some((origin, bsdf_pdf))) => {
+                        power_heuristic(bsdf_pdf, self.light_pdf(origin, &ray_hit, object))
+                    }
+                };
+                radiance = radiance + throughput * emitted * weight;
+            }
+
+            // 4. Light coming straight from a light, drawing from the dimensions of this bounce. The earlier bounces
+            // come first, the low dimensions being the best spread ones for most samplers. The light reached is one
+            // bounce further along the path, so none is sampled on the last bounce.
+            let dimension = CAMERA_DIMENSIONS + bounce * BOUNCE_DIMENSIONS;
+            let frame = Frame::from_normal(ray_hit.normal);
+            let wo = frame.to_local(ray.direction * -1f64);
+            if !material.is_specular() && bounce + 1 < settings.max_depth {
+                sampler.set_dimension(dimension);
+                let direct = self.direct_light(&ray_hit, material, &frame, wo, settings.light_sampling, sampler);
+                radiance = radiance + throughput * direct;
+            }
+
+            // 5. Sample the direction the light comes from in the shading frame. If the material does not sample a
+            // direction, the light has been absorbed
+            sampler.set_dimension(dimension + LIGHT_DIMENSIONS);
+            let Some(sample) = material.sample(wo, &ray_hit, sampler) else {
+                break;
+            };
+            if sample.pdf <= 0f64 {
+                break;
+            }
+            throughput = throughput * sample.f * (sample.wi.z.abs() / sample.pdf);
+            last_bounce = (!sample.specular).then_some((ray_hit.point, sample.pdf));
+            ray = Ray::new(ray_hit.point, frame.to_world(sample.wi));
+        }
+        radiance
+    }
+
+    /// Estimates the light scattered towards `wo` at a hit that comes straight from a light or an emissive object,
+    /// by sampling one of them and checking whether anything is in the way.
+    ///
+    /// Lights of no size can only be found this way. The light of the others is weighed against the chance of the
+    /// BSDF sampling the same direction, as `strategy` says.
+    ///
+    /// # Arguments
+    ///
+    /// * `hit` - Ref to the intersection lit
+    /// * `material` - the material at the intersection
+    /// * `frame` - the shading frame at the intersection
+    /// * `wo` - the direction the light is scattered towards, in the shading frame
+    /// * `strategy` - how light samples and BSDF samples are combined
+    /// * `sampler` - sampler of the thread tracing the ray, set to the light dimensions of the bounce
+    ///
+    /// # Returns
+    ///
+    /// * RgbColor - the light scattered towards `wo`, black when the light sampled is hidden
+    ///
+    fn direct_light(
+        &self,
+        hit: &Intersection,
+        material: &dyn Material,
+        frame: &Frame,
+        wo: Vector,
+        strategy: LightSampling,
+        sampler: &mut dyn Sampler,
+    ) -> RgbColor {
+        // 1. Pick a light and the direction towards it
+        let Some(light) = self.sample_light(hit.point, sampler) else {
+            return RgbColor::default();
+        };
+        // 2. Only light the material scatters towards `wo` counts
+        let wi = frame.to_local(light.wi);
+        let f = material.eval(wo, wi, hit);
+        let weight = match strategy {
+            _ if light.delta => 1f64,
+            LightSampling::Bsdf => 0f64,
+            LightSampling::Lights => 1f64,
+            LightSampling::Mis => power_heuristic(light.pdf, material.pdf(wo, wi, hit)),
+        };
+        if f == RgbColor::default() || weight == 0f64 {
             return RgbColor::default();
         }
+        // 3. Shadow ray, stopping short of the surface of an emitter
+        let shadow = Ray::new(hit.point, light.wi);
+        if self.occluded(&shadow, (RAY_EPSILON, light.distance - RAY_EPSILON)) {
+            return RgbColor::default();
+        }
+        f * light.radiance * (wi.z.abs() * weight / light.pdf)
+    }
 
-        // 2. Check if the input ray intersects an object in the world
-        if let Some((ray_hit, material)) = self.hit(&ray, (0.01, f64::INFINITY)) {
-            // 3. child ray bounces with material scattering
-            if let Some(child_ray) = material.scatter(ray, &ray_hit) {
-                return self.raytrace(&child_ray, depth - 1) * *material.albedo();
-            } else {
-                // 4. If the scatter function does not return a child ray, the incident ray has been absorbed
-                return RgbColor::default();
-            }
-        } else {
-            // 5. Nothing is hit, generate gradient background
-            let t: f64 = 0.5 * ray.direction.y + 1f64;
-            return RgbColor::new(1f64, 1f64, 1f64) * (1f64 - t)
-                + RgbColor::new(0.5f64, 0.7f64, 1f64) * t;
+    // Density `sample_light` picks the point of `hit` on `object` with from `origin`, per unit solid angle
+    fn light_pdf(&self, origin: Point, hit: &Intersection, object: &dyn Intersect) -> f64 {
+        object.surface_pdf(origin, hit) / (self.lights.len() + self.emitters.len()) as f64
+    }
+
+    /// Picks one of the lights and emissive objects of the world, with the same probability for all, and samples the
+    /// light it sends towards `point`. The probability of the pick is folded into the density of the sample.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - the point lit
+    /// * `sampler` - sampler of the thread tracing the ray, one dimension picks the light and two the point on it
+    ///
+    /// # Optionally returns (when the world has lights and the one picked can be seen from `point`)
+    ///
+    /// * `LightSample` - direction, distance and density of the light picked, and the light arriving from it
+    ///
+    fn sample_light(&self, point: Point, sampler: &mut dyn Sampler) -> Option<LightSample> {
+        let count = self.lights.len() + self.emitters.len();
+        if count == 0 {
+            return None;
         }
+        let choice = ((sampler.get_1d() * count as f64) as usize).min(count - 1);
+        let u = sampler.get_2d();
+
+        let sample = if choice < self.lights.len() {
+            self.lights[choice].sample(point)
+        } else {
+            let object = &self.contents[self.emitters[choice - self.lights.len()]];
+            let surface = object.sample_surface(point, u)?;
+            let to_light = surface.point - point;
+            let distance = to_light.len();
+            let wi = to_light / distance;
+            // The intersection a ray towards the point would find, with the normal facing the ray
+            let front_face = Vector::dot(surface.normal, wi) < 0f64;
+            let normal = if front_face { surface.normal } else { surface.normal * -1f64 };
+            let emitter_hit = Intersection::new(surface.point, normal, distance, front_face);
+            LightSample {
+                wi,
+                distance,
+                radiance: object.material().emitted(&emitter_hit),
+                pdf: surface.pdf,
+                delta: false,
+            }
+        };
+        Some(LightSample {
+            pdf: sample.pdf / count as f64,
+            ..sample
+        })
     }
 
-    /// Iterates over the content of the world to check whether the input ray intersects with the world content
-    /// within the given range and returns the intersection and material hit.
+    /// Walks the bounding volume hierarchy over the content of the world to check whether the input ray intersects
+    /// with the world content within the given range and returns the closest intersection and material hit.
     ///
     /// Since a ray in the world is simply a point (the source) and a vector (the direction), the function needs
     /// a range input within which the intersects function will solve the geometry.
@@ -177,35 +508,455 @@ impl World {
     /// # Optionally returns (when there is an intersection with the world content)
     ///
     /// * `Intersection` - Information about the intersection
-    /// * `&dyn Material<Albedo=RgbColor> - The material at intersection`
+    /// * `&dyn Intersect` - The object intersected, whose material scatters the ray
     ///
-    fn hit(
-        &self,
-        ray: &Ray,
-        range: (f64, f64),
-    ) -> Option<(Intersection, &dyn Material<Albedo = RgbColor>)> {
-        // 1. Initialize closest intersection to max of the input range
-        let mut closest_intersect = Intersection::new(Point::default(), Vector::default(), range.1);
-        // 2. Initialize the material hit to None
-        let mut material_hit: Option<&dyn Material<Albedo = RgbColor>> = None;
-
-        // For every object in the world i.e. our content vector
-        for object in &self.contents {
-            // 3. Check if the ray and object intersect
-            let object_intersect_range = (range.0, closest_intersect.t);
-            if let Some(intr) = object.intersects(ray, object_intersect_range) {
-                // 4. update closest intersection to find intersections between range.0 and the current intersection
-                closest_intersect = intr;
-                material_hit = Some(object.material());
+    fn hit(&self, ray: &Ray, range: (f64, f64)) -> Option<(Intersection, &dyn Intersect)> {
+        // 1. (Re)build the hierarchy if the world changed since the last ray
+        let bvh = self.bvh.get_or_init(|| Bvh::new(&self.contents));
+        // 2. Find the closest object hit, and return the intersection info and the object
+        bvh.hit(&self.contents, ray, range)
+            .map(|(intersection, index)| (intersection, &*self.contents[index]))
+    }
+
+    /// Walks the bounding volume hierarchy to check whether anything in the world is in the way of the input ray
+    /// within the given range, stopping at the first object found rather than looking for the closest one.
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` - Ref to the shadow ray
+    /// * `range` - The range within which the search for intersection will happen, up to the light
+    ///
+    /// # Returns
+    ///
+    /// * bool - true if any object intersects the ray within the range
+    ///
+    fn occluded(&self, ray: &Ray, range: (f64, f64)) -> bool {
+        let bvh = self.bvh.get_or_init(|| Bvh::new(&self.contents));
+        bvh.any_hit(&self.contents, ray, range)
+    }
+}
+
+// Veach's power heuristic with an exponent of 2, the weight of a sample drawn with density `pdf` by one strategy when
+// another would have drawn it with density `other`. The weights of the two strategies sum to 1.
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other * other);
+    if a.is_infinite() {
+        return 1f64;
+    }
+    if a + b == 0f64 {
+        return 0f64;
+    }
+    a / (a + b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::mesh::Mesh;
+    use crate::geometry::sphere::Sphere;
+    use crate::geometry::vec3::Vector;
+    use crate::geometry::vec3::Vector as Point;
+    use crate::scene::intersect::Intersection;
+    use crate::color::antialias::ReconstructionFilter;
+    use crate::scene::material::dielectric::Dielectric;
+    use crate::scene::material::light::DiffuseLight;
+    use crate::scene::material::matte::Matte;
+    use crate::scene::material::metal::Metal;
+    use crate::scene::sampler::{IndependentSampler, SamplerKind};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::f64::consts::{FRAC_1_PI, FRAC_1_SQRT_2};
+    use std::sync::Arc;
+
+    // Reference closest hit search, testing the ray against every object
+    fn hit_linear(world: &World, ray: &Ray, range: (f64, f64)) -> Option<(Intersection, usize)> {
+        let mut closest: Option<(Intersection, usize)> = None;
+        for (index, object) in world.contents.iter().enumerate() {
+            let t_max = closest.as_ref().map_or(range.1, |(intr, _)| intr.t);
+            if let Some(intr) = object.intersects(ray, (range.0, t_max)) {
+                closest = Some((intr, index));
             }
         }
+        closest
+    }
 
-        if let Some(material) = material_hit {
-            // 4. return the closest intersection info and material for scatter
-            return Some((closest_intersect, material));
+    #[test]
+    fn test_bvh_matches_linear_scan() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for spheres in [1, 7, 100, 2000] {
+            let mut world = World::new();
+            for _ in 0..spheres {
+                let center = Point::new(
+                    rng.gen_range(-20f64..20f64),
+                    rng.gen_range(-20f64..20f64),
+                    rng.gen_range(-20f64..20f64),
+                );
+                let material = Box::new(Metal::new(RgbColor::new(0.5f64, 0.5f64, 0.5f64), 0f64));
+                world.add(Box::new(Sphere::new(center, rng.gen_range(0.1f64..2f64), material)));
+            }
+
+            for _ in 0..2000 {
+                let origin = Point::new(
+                    rng.gen_range(-30f64..30f64),
+                    rng.gen_range(-30f64..30f64),
+                    rng.gen_range(-30f64..30f64),
+                );
+                let direction = Vector::new(
+                    rng.gen_range(-1f64..1f64),
+                    rng.gen_range(-1f64..1f64),
+                    rng.gen_range(-1f64..1f64),
+                );
+                let ray = Ray::new(origin, direction);
+                let range = (0.001f64, f64::INFINITY);
+
+                let expected = hit_linear(&world, &ray, range);
+                let actual = world.hit(&ray, range);
+                match (expected, actual) {
+                    (None, None) => {}
+                    (Some((expected, index)), Some((actual, object))) => {
+                        assert_eq!(expected.t, actual.t);
+                        assert_eq!(expected.point, actual.point);
+                        assert_eq!(expected.normal, actual.normal);
+                        assert_eq!(expected.front_face, actual.front_face);
+                        assert!(std::ptr::eq(
+                            &*world.contents[index] as *const _ as *const u8,
+                            object as *const _ as *const u8
+                        ));
+                    }
+                    (expected, actual) => panic!(
+                        "linear scan hit: {}, bvh hit: {}",
+                        expected.is_some(),
+                        actual.is_some()
+                    ),
+                }
+            }
         }
+    }
+
+    #[test]
+    fn test_render_threads_layout() {
+        let mut world = World::new();
+        world.add(Box::new(Sphere::new(
+            Point::new(0f64, -100.5f64, -1f64),
+            100f64,
+            Box::new(Matte::new(RgbColor::new(0.5f64, 0.5f64, 0.5f64))),
+        )));
+        let camera = Camera::default();
+
+        for threads in [1, 4] {
+            let settings = RenderSettings {
+                threads,
+                ..RenderSettings::with_aspect(32, camera.aspect_ratio)
+            };
+            let render = world.render(&camera, &settings);
+            assert_eq!(render.len(), 32 * 18);
+            // The top row sees the blue sky and the bottom row the darker grey ground
+            for x in 0..32 {
+                let (top, bottom) = (render[x], render[17 * 32 + x]);
+                assert!(top.z > top.x);
+                assert!(bottom.z < top.z);
+            }
+        }
+    }
+
+    #[test]
+    fn test_seeded_render_is_identical_across_threads() {
+        let mut world = World::new();
+        world.add(Box::new(Sphere::new(
+            Point::new(0f64, -100.5f64, -1f64),
+            100f64,
+            Box::new(Matte::new(RgbColor::new(0.5f64, 0.8f64, 0.2f64))),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point::new(-0.6f64, 0f64, -1f64),
+            0.5f64,
+            Box::new(Metal::new(RgbColor::new(0.8f64, 0.6f64, 0.2f64), 0.3f64)),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point::new(0.6f64, 0f64, -1f64),
+            0.5f64,
+            Box::new(Dielectric::new(RgbColor::new(1f64, 1f64, 1f64), 1.5f64)),
+        )));
+        let camera = Camera::default();
+
+        for sampler in [SamplerKind::Independent, SamplerKind::Sobol] {
+            let settings = |seed: u64, threads: usize| RenderSettings {
+                samples_per_pixel: 4,
+                seed: Some(seed),
+                threads,
+                sampler,
+                filter: ReconstructionFilter::Mitchell(2f64),
+                ..RenderSettings::with_aspect(24, camera.aspect_ratio)
+            };
+            let reference = world.render(&camera, &settings(7, 1));
+            for threads in [2, 3, 8] {
+                assert!(world.render(&camera, &settings(7, threads)) == reference, "{} threads", threads);
+            }
+            assert!(world.render(&camera, &settings(8, 3)) != reference);
+        }
+    }
+
+    #[test]
+    fn test_samples_cover_pixel_uniformly() {
+        let mut world = World::new();
+        world.set_background(Background::Solid(RgbColor::default()));
+        let camera = Camera::default();
+        let settings = RenderSettings {
+            samples_per_pixel: 16_000,
+            ..RenderSettings::with_aspect(16, camera.aspect_ratio)
+        };
+        let kinds = [
+            SamplerKind::Independent,
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+        ];
+
+        // Corner pixels included, the first and last columns used to get no jitter at all
+        for (kind, pixel) in kinds.iter().flat_map(|&kind| [(0, 0), (7, 4), (15, 8)].map(|pixel| (kind, pixel))) {
+            let mut sampler = kind.build(settings.samples_per_pixel, 0x5eed);
+            // Chi-square test of the sample positions binned on an 8 x 8 grid over the pixel
+            let mut bins = [0usize; 64];
+            let mut extent = (f64::INFINITY, f64::NEG_INFINITY);
+            world.trace_pixel(&camera, pixel, &settings, sampler.as_mut(), |position, _| {
+                let (dx, dy) = (position.0 - pixel.0 as f64, position.1 - pixel.1 as f64);
+                assert!((0f64..1f64).contains(&dx) && (0f64..1f64).contains(&dy));
+                bins[(dx * 8f64) as usize * 8 + (dy * 8f64) as usize] += 1;
+                extent = (extent.0.min(dx.min(dy)), extent.1.max(dx.max(dy)));
+            });
+            let expected = settings.samples_per_pixel as f64 / 64f64;
+            let chi_square: f64 = bins
+                .iter()
+                .map(|&count| (count as f64 - expected).powi(2) / expected)
+                .sum();
+            // 99.9th percentile of the chi-square distribution with 63 degrees of freedom
+            assert!(chi_square < 103.4, "{} pixel {:?}: chi-square {}", kind, pixel, chi_square);
+            assert!(extent.0 < 0.01 && extent.1 > 0.99);
+        }
+    }
+
+    #[test]
+    fn test_emitters_light_a_dark_world() {
+        let mut world = World::new();
+        world.set_background(Background::Solid(RgbColor::default()));
+        let camera = Camera::default();
+        let settings = RenderSettings::with_aspect(16, camera.aspect_ratio);
+
+        // Nothing but the black background
+        let render = world.render(&camera, &settings);
+        assert!(render.iter().all(|pixel| *pixel == RgbColor::default()));
+
+        // A lamp filling the middle of the view, in front of a wall that only it lights up
+        world.add(Box::new(Sphere::new(
+            Point::new(0f64, 0f64, -1f64),
+            0.5f64,
+            Box::new(DiffuseLight::new(RgbColor::new(4f64, 2f64, 1f64))),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point::new(0f64, 0f64, -1001f64),
+            999f64,
+            Box::new(Matte::new(RgbColor::new(0.5f64, 0.5f64, 0.5f64))),
+        )));
+        let render = world.render(&camera, &settings);
+        assert_eq!(render[4 * 16 + 8], RgbColor::new(4f64, 2f64, 1f64));
+        // Pixels drawn one at a time, as in previews, go through the same samples
+        let pixel = world.render_pixel(&camera, (8, 4), &settings, &mut IndependentSampler::new(1));
+        assert_eq!(pixel, RgbColor::new(4f64, 2f64, 1f64));
+        assert!(render.iter().any(|pixel| pixel.x > 0f64 && pixel.x < 4f64));
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // Under a uniform white sky, every bounce off a convex diffuse object escapes and brings back exactly the
+        // albedo: samples weighed by f * cos / pdf have no variance
+        let mut world = World::new();
+        world.set_background(Background::Solid(RgbColor::new(1f64, 1f64, 1f64)));
+        let albedo = RgbColor::new(0.8f64, 0.5f64, 0.2f64);
+        world.add(Box::new(Sphere::new(
+            Point::new(0f64, 0f64, -1f64),
+            0.5f64,
+            Box::new(Matte::new(albedo)),
+        )));
+        let camera = Camera::default();
+        let settings = RenderSettings::with_aspect(16, camera.aspect_ratio);
+        let render = world.render(&camera, &settings);
+        assert!((render[4 * 16 + 8] - albedo).len() < 1e-9);
+        assert_eq!(render[0], RgbColor::new(1f64, 1f64, 1f64));
+    }
+
+    #[test]
+    fn test_parse_light_sampling() {
+        for strategy in [LightSampling::Bsdf, LightSampling::Lights, LightSampling::Mis] {
+            assert_eq!(strategy.to_string().parse(), Ok(strategy));
+        }
+        assert!("both".parse::<LightSampling>().is_err());
+    }
+
+    #[test]
+    fn test_power_heuristic() {
+        assert!((power_heuristic(1f64, 3f64) - 0.1f64).abs() < 1e-12);
+        assert!((power_heuristic(1f64, 3f64) + power_heuristic(3f64, 1f64) - 1f64).abs() < 1e-12);
+        assert_eq!(power_heuristic(f64::INFINITY, 2f64), 1f64);
+        assert_eq!(power_heuristic(0f64, 0f64), 0f64);
+    }
+
+    // A dark world with a matte floor through y = 0, seen straight down through a narrow single pixel camera
+    fn lit_floor(albedo: RgbColor) -> (World, Camera, RenderSettings) {
+        let mut world = World::new();
+        world.set_background(Background::Solid(RgbColor::default()));
+        world.add(Box::new(Sphere::new(
+            Point::new(0f64, -1000f64, 0f64),
+            1000f64,
+            Box::new(Matte::new(albedo)),
+        )));
+        let camera = Camera::look_at(
+            Point::new(0f64, 1f64, 0f64),
+            Point::default(),
+            Vector::new(0f64, 0f64, -1f64),
+            0.5f64,
+            1f64,
+        );
+        let settings = RenderSettings {
+            samples_per_pixel: 256,
+            max_depth: 4,
+            seed: Some(11),
+            ..RenderSettings::new(1, 1)
+        };
+        (world, camera, settings)
+    }
+
+    #[test]
+    fn test_direct_lighting_matches_analytic() {
+        // Light leaving a Lambertian floor is albedo / π times the light falling on it
+        let albedo = RgbColor::new(0.8f64, 0.5f64, 0.2f64);
+        let close = |a: RgbColor, b: RgbColor, tolerance: f64| (a - b).len() < tolerance * b.len();
+
+        // A point light 2 above: I cosθ / d²
+        let (mut world, camera, settings) = lit_floor(albedo);
+        world.add_light(Light::Point {
+            position: Point::new(0f64, 2f64, 0f64),
+            intensity: RgbColor::new(8f64, 8f64, 8f64),
+        });
+        let pixel = world.render(&camera, &settings)[0];
+        assert!(close(pixel, albedo * (2f64 * FRAC_1_PI), 1e-3), "{:?}", pixel);
+
+        // Sunlight at 45 degrees: E cosθ
+        let (mut world, camera, settings) = lit_floor(albedo);
+        world.add_light(Light::Directional {
+            direction: Vector::new(0f64, -1f64, 1f64),
+            irradiance: RgbColor::new(2f64, 2f64, 2f64),
+        });
+        let pixel = world.render(&camera, &settings)[0];
+        assert!(close(pixel, albedo * (2f64 * FRAC_1_SQRT_2 * FRAC_1_PI), 1e-3), "{:?}", pixel);
+
+        // A glowing ball of radius r, d above: π L sin²θmax = π L r² / d²
+        let (mut world, camera, settings) = lit_floor(albedo);
+        world.add(Box::new(Sphere::new(
+            Point::new(0f64, 3f64, 0f64),
+            0.5f64,
+            Box::new(DiffuseLight::new(RgbColor::new(9f64, 9f64, 9f64))),
+        )));
+        let pixel = world.render(&camera, &settings)[0];
+        assert!(close(pixel, albedo * 0.25f64, 0.02), "{:?}", pixel);
+    }
+
+    #[test]
+    fn test_shadows() {
+        // A ball between the light and the floor casts a shadow, and only the ball is lit
+        let (mut world, camera, settings) = lit_floor(RgbColor::new(0.5f64, 0.5f64, 0.5f64));
+        world.add_light(Light::Point {
+            position: Point::new(0f64, 4f64, 0f64),
+            intensity: RgbColor::new(8f64, 8f64, 8f64),
+        });
+        let pixel = world.render(&camera, &settings)[0];
+        assert!(pixel.x > 0f64);
+        world.add(Box::new(Sphere::new(
+            Point::new(0f64, 3f64, 0f64),
+            0.2f64,
+            Box::new(Matte::new(RgbColor::new(0.5f64, 0.5f64, 0.5f64))),
+        )));
+        // The ball reflects some light back down, but none straight from the light reaches the floor
+        let shadowed = world.render(&camera, &settings)[0];
+        assert!(shadowed.x < pixel.x * 0.05, "{:?} {:?}", shadowed, pixel);
+        let ray = Ray::new(Point::default(), Vector::new(0f64, 1f64, 0f64));
+        assert!(world.occluded(&ray, (RAY_EPSILON, 4f64)));
+        assert!(!world.occluded(&ray, (RAY_EPSILON, 2.5f64)));
+    }
+
+    // Mean red of the glossy plates rendered at 36 x 24 with a depth of 2, from 131072 samples per pixel with the BSDF
+    // and the light strategies alone, neither of which depends on the MIS weights
+    const VEACH_PLATES_MEAN: f64 = 0.354;
+
+    // Veach's glossy plates: four metal plates of decreasing roughness, tilted to reflect four glowing balls of
+    // increasing size and equal power towards the camera
+    fn veach_plates() -> (World, Camera) {
+        let mut world = World::new();
+        world.set_background(Background::Solid(RgbColor::default()));
+        let camera = Camera::look_at(
+            Point::new(0f64, 2f64, 15f64),
+            Point::new(0f64, -2.8f64, 2.4f64),
+            Vector::new(0f64, 1f64, 0f64),
+            20f64,
+            1.5f64,
+        );
+        let (eye, lights) = (Point::new(0f64, 2f64, 15f64), Point::new(0f64, 0f64, 0f64));
+        for (i, fuzz) in [0.3f64, 0.15f64, 0.06f64, 0.02f64].into_iter().enumerate() {
+            let center = Point::new(0f64, -3.4f64 + 0.5f64 * i as f64, 4.2f64 - 1.2f64 * i as f64);
+            // Tilted halfway between the directions to the camera and to the balls, a mirror reflecting them
+            let normal = Vector::unit(Vector::unit(eye - center) + Vector::unit(lights - center));
+            let across = Vector::new(4f64, 0f64, 0f64);
+            let along = Vector::unit(Vector::cross(normal, across)) * 0.5f64;
+            let corners = vec![
+                center - across - along,
+                center + across - along,
+                center + across + along,
+                center - across + along,
+            ];
+            let material = Arc::new(Metal::new(RgbColor::new(0.8f64, 0.8f64, 0.8f64), fuzz));
+            let plate = Mesh::new(corners, vec![[0, 1, 2], [0, 2, 3]], material);
+            world.add(Box::new(plate));
+        }
+        for (x, radius) in [(-3.75f64, 0.05f64), (-1.25f64, 0.15f64), (1.25f64, 0.4f64), (3.75f64, 1f64)] {
+            // The same power for every ball, the radiance falling with the area
+            let emit = 0.8f64 / (radius * radius);
+            world.add(Box::new(Sphere::new(
+                Point::new(x, 0f64, 0f64),
+                radius,
+                Box::new(DiffuseLight::new(RgbColor::new(emit, emit, emit))),
+            )));
+        }
+        (world, camera)
+    }
+
+    #[test]
+    fn test_veach_plates() {
+        let (world, camera) = veach_plates();
+        let render = |light_sampling, samples_per_pixel, seed| {
+            let settings = RenderSettings {
+                samples_per_pixel,
+                max_depth: 2,
+                light_sampling,
+                seed: Some(seed),
+                ..RenderSettings::new(36, 24)
+            };
+            world.render(&camera, &settings)
+        };
+        let mean = |image: &[RgbColor]| image.iter().map(|p| p.x).sum::<f64>() / image.len() as f64;
+
+        // 1. Each strategy alone is noisy where the other does well, small balls in rough plates for the BSDF and large
+        // balls in smooth plates for the lights. The noise is measured between two renders with different seeds, and
+        // combined the strategies beat both.
+        let [bsdf, lights, mis] = [LightSampling::Bsdf, LightSampling::Lights, LightSampling::Mis].map(|strategy| {
+            let (a, b) = (render(strategy, 32, 2), render(strategy, 32, 3));
+            let average = (mean(&a) + mean(&b)) / 2f64;
+            assert!((average / VEACH_PLATES_MEAN - 1f64).abs() < 0.1f64, "{} {}", strategy, average);
+            a.iter().zip(&b).map(|(a, b)| (a.x - b.x).abs()).sum::<f64>() / (average * a.len() as f64)
+        });
+        assert!(mis < 0.3f64, "{}", mis);
+        assert!(mis < 0.6f64 * bsdf && mis < 0.6f64 * lights, "{} {} {}", bsdf, lights, mis);
 
-        // 5. We did not hit anything in our world
-        None
+        // 2. Weights that do not add up to one for every path bias the image, which shows in its mean
+        let image = render(LightSampling::Mis, 256, 4);
+        assert!((mean(&image) / VEACH_PLATES_MEAN - 1f64).abs() < 0.03f64, "{}", mean(&image));
     }
 }